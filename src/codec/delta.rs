@@ -0,0 +1,282 @@
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use xcap::image::RgbaImage;
+
+use crate::codec::EncodedFrame;
+
+/// Lato dei tile (in pixel) in cui viene diviso il frame.
+pub const DEFAULT_TILE_SIZE: u32 = 64;
+
+/// Porzione rettangolare del frame, con i pixel RGBA riga per riga.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Tile {
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+    data: Vec<u8>,
+}
+
+#[derive(Error, Debug)]
+pub enum DeltaError {
+    #[error("Delta frame {0} has no valid reference, waiting for a keyframe")]
+    MissingReference(u32),
+    #[error("Frame data does not match its declared size")]
+    InvalidSize,
+    #[error("Frame is not tile encoded")]
+    UnsupportedFrame,
+}
+
+/// Confronta ogni frame con il precedente e produce i tile modificati,
+/// con un keyframe completo a intervalli regolari.
+pub struct DeltaEncoder {
+    previous: Option<RgbaImage>,
+    tile_size: u32,
+    keyframe_interval: u32,
+    frames_since_keyframe: u32,
+    sequence: u32,
+    force_keyframe: bool,
+}
+
+impl DeltaEncoder {
+    pub fn new(tile_size: u32, keyframe_interval: u32) -> Self {
+        DeltaEncoder {
+            previous: None,
+            tile_size: tile_size.max(1),
+            keyframe_interval: keyframe_interval.max(1),
+            frames_since_keyframe: 0,
+            sequence: 0,
+            force_keyframe: true,
+        }
+    }
+
+    /// Il prossimo frame sarà un keyframe (ad es. quando si collega un nuovo receiver).
+    pub fn force_keyframe(&mut self) {
+        self.force_keyframe = true;
+    }
+
+    pub fn encode(&mut self, frame: &RgbaImage) -> EncodedFrame {
+        let sequence = self.sequence;
+        self.sequence = self.sequence.wrapping_add(1);
+
+        let previous = match self.previous.take() {
+            Some(previous)
+                if !self.force_keyframe
+                    && self.frames_since_keyframe + 1 < self.keyframe_interval
+                    && previous.dimensions() == frame.dimensions() =>
+            {
+                previous
+            }
+            _ => {
+                self.force_keyframe = false;
+                self.frames_since_keyframe = 0;
+                self.previous = Some(frame.clone());
+                return EncodedFrame::Key {
+                    sequence,
+                    width: frame.width(),
+                    height: frame.height(),
+                    data: frame.as_raw().clone(),
+                };
+            }
+        };
+
+        self.frames_since_keyframe += 1;
+        let (width, height) = frame.dimensions();
+        let mut tiles = Vec::new();
+
+        for y in (0..height).step_by(self.tile_size as usize) {
+            for x in (0..width).step_by(self.tile_size as usize) {
+                let tile_width = self.tile_size.min(width - x);
+                let tile_height = self.tile_size.min(height - y);
+                if tile_changed(&previous, frame, x, y, tile_width, tile_height) {
+                    tiles.push(Tile {
+                        x,
+                        y,
+                        width: tile_width,
+                        height: tile_height,
+                        data: copy_tile(frame, x, y, tile_width, tile_height),
+                    });
+                }
+            }
+        }
+
+        self.previous = Some(frame.clone());
+        EncodedFrame::Delta {
+            sequence,
+            width,
+            height,
+            tiles,
+        }
+    }
+}
+
+/// Ricostruisce i frame applicando i tile ricevuti all'ultima immagine.
+#[derive(Default)]
+pub struct DeltaDecoder {
+    current: Option<RgbaImage>,
+    last_sequence: Option<u32>,
+}
+
+impl DeltaDecoder {
+    pub fn decode(&mut self, frame: EncodedFrame) -> Result<RgbaImage, DeltaError> {
+        match frame {
+            EncodedFrame::Key {
+                sequence,
+                width,
+                height,
+                data,
+            } => {
+                let image = RgbaImage::from_raw(width, height, data).ok_or(DeltaError::InvalidSize)?;
+                self.current = Some(image.clone());
+                self.last_sequence = Some(sequence);
+                Ok(image)
+            }
+            EncodedFrame::Delta {
+                sequence,
+                width,
+                height,
+                tiles,
+            } => {
+                // Un delta si applica solo al frame immediatamente precedente
+                let expected = self.last_sequence.map(|last| last.wrapping_add(1));
+                let current = match self.current.as_mut() {
+                    Some(current)
+                        if expected == Some(sequence) && current.dimensions() == (width, height) =>
+                    {
+                        current
+                    }
+                    _ => {
+                        self.last_sequence = None;
+                        return Err(DeltaError::MissingReference(sequence));
+                    }
+                };
+
+                for tile in &tiles {
+                    if !paste_tile(current, tile) {
+                        self.last_sequence = None;
+                        return Err(DeltaError::InvalidSize);
+                    }
+                }
+                self.last_sequence = Some(sequence);
+                Ok(current.clone())
+            }
+            EncodedFrame::H264 { .. } => Err(DeltaError::UnsupportedFrame),
+        }
+    }
+}
+
+fn row_range(image: &RgbaImage, x: u32, y: u32, width: u32) -> std::ops::Range<usize> {
+    let start = ((y * image.width() + x) * 4) as usize;
+    start..start + (width * 4) as usize
+}
+
+fn tile_changed(previous: &RgbaImage, frame: &RgbaImage, x: u32, y: u32, width: u32, height: u32) -> bool {
+    (y..y + height).any(|row| {
+        let range = row_range(frame, x, row, width);
+        previous.as_raw()[range.clone()] != frame.as_raw()[range]
+    })
+}
+
+fn copy_tile(frame: &RgbaImage, x: u32, y: u32, width: u32, height: u32) -> Vec<u8> {
+    let mut data = Vec::with_capacity((width * height * 4) as usize);
+    for row in y..y + height {
+        data.extend_from_slice(&frame.as_raw()[row_range(frame, x, row, width)]);
+    }
+    data
+}
+
+fn paste_tile(image: &mut RgbaImage, tile: &Tile) -> bool {
+    let fits = tile.x + tile.width <= image.width()
+        && tile.y + tile.height <= image.height()
+        && tile.data.len() == (tile.width * tile.height * 4) as usize;
+    if !fits {
+        return false;
+    }
+    let row_len = (tile.width * 4) as usize;
+    for (i, row) in (tile.y..tile.y + tile.height).enumerate() {
+        let range = row_range(image, tile.x, row, tile.width);
+        let buffer: &mut [u8] = image;
+        buffer[range].copy_from_slice(&tile.data[i * row_len..(i + 1) * row_len]);
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(width: u32, height: u32, value: u8) -> RgbaImage {
+        RgbaImage::from_pixel(width, height, xcap::image::Rgba([value, value, value, 255]))
+    }
+
+    #[test]
+    fn only_changed_tiles_are_sent() {
+        let mut encoder = DeltaEncoder::new(16, 30);
+        let mut decoder = DeltaDecoder::default();
+
+        let first = frame(40, 24, 0);
+        let key = encoder.encode(&first);
+        assert!(matches!(key, EncodedFrame::Key { .. }));
+
+        let mut second = first.clone();
+        second.put_pixel(20, 20, xcap::image::Rgba([255, 0, 0, 255]));
+        let delta = encoder.encode(&second);
+        match &delta {
+            // (20, 20) cade nel tile di bordo in basso, largo 16 e alto 8
+            EncodedFrame::Delta { tiles, .. } => {
+                assert_eq!(tiles.len(), 1);
+                assert_eq!((tiles[0].x, tiles[0].y, tiles[0].width, tiles[0].height), (16, 16, 16, 8));
+            }
+            other => panic!("atteso un delta, ricevuto {:?}", other),
+        }
+
+        decoder.decode(key).unwrap();
+        assert_eq!(decoder.decode(delta).unwrap(), second);
+    }
+
+    #[test]
+    fn keyframes_follow_the_interval_and_size_changes() {
+        let mut encoder = DeltaEncoder::new(16, 3);
+        let image = frame(32, 32, 7);
+        let kinds: Vec<bool> = (0..6)
+            .map(|_| matches!(encoder.encode(&image), EncodedFrame::Key { .. }))
+            .collect();
+        assert_eq!(kinds, [true, false, false, true, false, false]);
+
+        assert!(matches!(encoder.encode(&frame(48, 32, 7)), EncodedFrame::Key { .. }));
+        encoder.force_keyframe();
+        assert!(matches!(encoder.encode(&frame(48, 32, 7)), EncodedFrame::Key { .. }));
+    }
+
+    #[test]
+    fn delta_without_reference_waits_for_a_keyframe() {
+        let mut encoder = DeltaEncoder::new(16, 30);
+        let mut decoder = DeltaDecoder::default();
+
+        let key = encoder.encode(&frame(32, 32, 0));
+        let lost = encoder.encode(&frame(32, 32, 1));
+        let next = encoder.encode(&frame(32, 32, 2));
+
+        // Un delta senza alcun frame precedente
+        assert!(matches!(
+            decoder.decode(lost.clone()),
+            Err(DeltaError::MissingReference(1))
+        ));
+
+        // Dopo il keyframe, il delta 2 arriva senza il delta 1 perso
+        decoder.decode(key).unwrap();
+        assert!(matches!(
+            decoder.decode(next),
+            Err(DeltaError::MissingReference(2))
+        ));
+        // Finché non arriva un keyframe anche il delta mancante viene rifiutato
+        assert!(matches!(
+            decoder.decode(lost),
+            Err(DeltaError::MissingReference(1))
+        ));
+
+        encoder.force_keyframe();
+        let recovered = encoder.encode(&frame(32, 32, 3));
+        assert_eq!(decoder.decode(recovered).unwrap(), frame(32, 32, 3));
+    }
+}
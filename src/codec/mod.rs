@@ -0,0 +1,132 @@
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use xcap::image::RgbaImage;
+
+use crate::codec::delta::{DeltaDecoder, DeltaEncoder, DeltaError, DEFAULT_TILE_SIZE};
+use crate::codec::video::{CodecSettings, H264Decoder, H264Encoder, VideoCodec};
+
+pub mod delta;
+pub mod video;
+
+/// Frame codificato così come viaggia verso i receiver.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub enum EncodedFrame {
+    /// Frame RGBA completo
+    Key {
+        sequence: u32,
+        width: u32,
+        height: u32,
+        data: Vec<u8>,
+    },
+    /// Solo i tile cambiati rispetto al frame precedente
+    Delta {
+        sequence: u32,
+        width: u32,
+        height: u32,
+        tiles: Vec<delta::Tile>,
+    },
+    /// Bitstream H.264 (Annex B) di un frame
+    H264 {
+        width: u32,
+        height: u32,
+        data: Vec<u8>,
+    },
+}
+
+impl EncodedFrame {
+    pub fn width(&self) -> u32 {
+        match self {
+            EncodedFrame::Key { width, .. }
+            | EncodedFrame::Delta { width, .. }
+            | EncodedFrame::H264 { width, .. } => *width,
+        }
+    }
+
+    pub fn height(&self) -> u32 {
+        match self {
+            EncodedFrame::Key { height, .. }
+            | EncodedFrame::Delta { height, .. }
+            | EncodedFrame::H264 { height, .. } => *height,
+        }
+    }
+
+    /// Vero se il frame si decodifica senza i precedenti.
+    pub fn is_keyframe(&self) -> bool {
+        match self {
+            EncodedFrame::Key { .. } => true,
+            EncodedFrame::Delta { .. } => false,
+            EncodedFrame::H264 { data, .. } => video::contains_idr(data),
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum CodecError {
+    #[error(transparent)]
+    Delta(#[from] DeltaError),
+    #[error("H.264 error: {0}")]
+    H264(#[from] openh264::Error),
+}
+
+/// Encoder scelto per la sessione di streaming.
+pub enum FrameEncoder {
+    Delta(DeltaEncoder),
+    H264(H264Encoder),
+}
+
+impl FrameEncoder {
+    pub fn new(settings: &CodecSettings, fps: u32) -> Result<Self, CodecError> {
+        match settings.codec {
+            VideoCodec::Delta => Ok(FrameEncoder::Delta(DeltaEncoder::new(
+                DEFAULT_TILE_SIZE,
+                settings.keyframe_interval,
+            ))),
+            VideoCodec::H264 => Ok(FrameEncoder::H264(H264Encoder::new(settings, fps)?)),
+        }
+    }
+
+    /// Come `new`, ma ripiega sulla codifica a tile se l'encoder video non è disponibile.
+    pub fn new_or_fallback(settings: &CodecSettings, fps: u32) -> Self {
+        Self::new(settings, fps).unwrap_or_else(|e| {
+            eprintln!("Encoder {:?} non disponibile ({}), uso i tile", settings.codec, e);
+            FrameEncoder::Delta(DeltaEncoder::new(DEFAULT_TILE_SIZE, settings.keyframe_interval))
+        })
+    }
+
+    pub fn force_keyframe(&mut self) {
+        match self {
+            FrameEncoder::Delta(encoder) => encoder.force_keyframe(),
+            FrameEncoder::H264(encoder) => encoder.force_keyframe(),
+        }
+    }
+
+    pub fn encode(&mut self, frame: &RgbaImage) -> Result<EncodedFrame, CodecError> {
+        match self {
+            FrameEncoder::Delta(encoder) => Ok(encoder.encode(frame)),
+            FrameEncoder::H264(encoder) => encoder.encode(frame),
+        }
+    }
+}
+
+/// Decoder lato receiver: gestisce qualunque codifica scelta dal caster.
+#[derive(Default)]
+pub struct FrameDecoder {
+    delta: DeltaDecoder,
+    h264: Option<H264Decoder>,
+}
+
+impl FrameDecoder {
+    /// Restituisce `None` se il decoder ha bisogno di altri dati prima di produrre un'immagine.
+    pub fn decode(&mut self, frame: EncodedFrame) -> Result<Option<RgbaImage>, CodecError> {
+        match frame {
+            EncodedFrame::H264 { data, .. } => {
+                let decoder = match self.h264.as_mut() {
+                    Some(decoder) => decoder,
+                    None => self.h264.insert(H264Decoder::new()?),
+                };
+                decoder.decode(&data)
+            }
+            frame => Ok(Some(self.delta.decode(frame)?)),
+        }
+    }
+}
@@ -0,0 +1,149 @@
+use std::fmt;
+
+use openh264::decoder::Decoder;
+use openh264::encoder::{BitRate, Encoder, EncoderConfig, FrameRate, IntraFramePeriod};
+use openh264::formats::{RgbaSliceU8, YUVBuffer, YUVSource};
+use openh264::OpenH264API;
+use serde::{Deserialize, Serialize};
+use xcap::image::{imageops, RgbaImage};
+
+use crate::codec::{CodecError, EncodedFrame};
+
+/// Codifica usata per i frame della sessione.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum VideoCodec {
+    /// Tile RGBA senza perdita (vedi `codec::delta`)
+    #[default]
+    Delta,
+    /// H.264 software tramite OpenH264, non richiede GPU
+    H264,
+}
+
+impl VideoCodec {
+    pub const ALL: [VideoCodec; 2] = [VideoCodec::Delta, VideoCodec::H264];
+}
+
+impl fmt::Display for VideoCodec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VideoCodec::Delta => write!(f, "Lossless tiles"),
+            VideoCodec::H264 => write!(f, "H.264"),
+        }
+    }
+}
+
+/// Parametri di codifica scelti dal caster per la sessione.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CodecSettings {
+    pub codec: VideoCodec,
+    pub bitrate_kbps: u32,
+    pub keyframe_interval: u32,
+}
+
+impl CodecSettings {
+    pub const BITRATES_KBPS: [u32; 5] = [1_000, 2_500, 5_000, 8_000, 12_000];
+    pub const KEYFRAME_INTERVALS: [u32; 4] = [15, 30, 60, 120];
+}
+
+impl Default for CodecSettings {
+    fn default() -> Self {
+        CodecSettings {
+            codec: VideoCodec::default(),
+            bitrate_kbps: 5_000,
+            keyframe_interval: 30,
+        }
+    }
+}
+
+const NAL_TYPE_IDR: u8 = 5; // Slice di un frame decodificabile da solo
+
+/// Vero se il bitstream Annex B contiene una slice IDR, da cui il decoder può ripartire.
+pub fn contains_idr(data: &[u8]) -> bool {
+    data.windows(4)
+        .any(|nal| nal[..3] == [0, 0, 1] && nal[3] & 0x1f == NAL_TYPE_IDR)
+}
+
+/// Encoder H.264: converte i frame RGBA in YUV420 e li comprime con OpenH264.
+pub struct H264Encoder {
+    encoder: Encoder,
+}
+
+impl H264Encoder {
+    pub fn new(settings: &CodecSettings, fps: u32) -> Result<Self, CodecError> {
+        let config = EncoderConfig::new()
+            .bitrate(BitRate::from_bps(settings.bitrate_kbps * 1000))
+            .max_frame_rate(FrameRate::from_hz(fps.max(1) as f32))
+            .intra_frame_period(IntraFramePeriod::from_num_frames(settings.keyframe_interval));
+        let encoder = Encoder::with_api_config(OpenH264API::from_source(), config)?;
+        Ok(H264Encoder { encoder })
+    }
+
+    pub fn force_keyframe(&mut self) {
+        self.encoder.force_intra_frame();
+    }
+
+    pub fn encode(&mut self, frame: &RgbaImage) -> Result<EncodedFrame, CodecError> {
+        // YUV420 richiede dimensioni pari: scarta l'eventuale ultima riga/colonna
+        let (width, height) = (frame.width() & !1, frame.height() & !1);
+        let even_frame;
+        let frame = if (width, height) == frame.dimensions() {
+            frame
+        } else {
+            even_frame = imageops::crop_imm(frame, 0, 0, width, height).to_image();
+            &even_frame
+        };
+
+        let rgba = RgbaSliceU8::new(frame.as_raw(), (width as usize, height as usize));
+        let yuv = YUVBuffer::from_rgb_source(rgba);
+        let bitstream = self.encoder.encode(&yuv)?;
+
+        Ok(EncodedFrame::H264 {
+            width,
+            height,
+            data: bitstream.to_vec(),
+        })
+    }
+}
+
+/// Decoder H.264 del receiver, riconverte i frame YUV420 in RGBA.
+pub struct H264Decoder {
+    decoder: Decoder,
+}
+
+impl H264Decoder {
+    pub fn new() -> Result<Self, CodecError> {
+        Ok(H264Decoder {
+            decoder: Decoder::new()?,
+        })
+    }
+
+    pub fn decode(&mut self, data: &[u8]) -> Result<Option<RgbaImage>, CodecError> {
+        // L'encoder può saltare dei frame producendo un bitstream vuoto
+        if data.is_empty() {
+            return Ok(None);
+        }
+        let Some(yuv) = self.decoder.decode(data)? else {
+            return Ok(None);
+        };
+
+        let (width, height) = yuv.dimensions();
+        let mut rgba = vec![0u8; width * height * 4];
+        yuv.write_rgba8(&mut rgba);
+        Ok(RgbaImage::from_raw(width as u32, height as u32, rgba))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn idr_slices_are_found_after_any_start_code() {
+        // SPS, PPS e slice IDR, con start code da 4 e da 3 byte
+        let keyframe = [0, 0, 0, 1, 0x67, 0x42, 0, 0, 0, 1, 0x68, 0xce, 0, 0, 1, 0x65, 0x88];
+        let predicted = [0, 0, 0, 1, 0x41, 0x9a, 0, 0, 1, 0x41, 0x9b];
+        assert!(contains_idr(&keyframe));
+        assert!(!contains_idr(&predicted));
+        assert!(!contains_idr(&[]));
+    }
+}
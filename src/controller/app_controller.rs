@@ -1,6 +1,10 @@
-use crate::screenshare::screenshare::{start_partial_sharing, start_screen_sharing, take_screenshot,};
-use crate::socket::socket::CasterSocket;
-use std::sync::atomic::{AtomicBool, Ordering};
+use crate::codec::video::CodecSettings;
+use crate::screenshare::scaling::OutputScaling;
+use crate::screenshare::screenshare::{
+    start_partial_sharing, start_screen_sharing, take_screenshot, DEFAULT_TARGET_FPS,
+};
+use crate::socket::socket::{CasterSocket, JoinRequest, StreamStatus};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
 use std::sync::Arc;
 use tokio::sync::Mutex;
 use xcap::image::RgbaImage;
@@ -14,6 +18,11 @@ pub struct AppController {
     sender: Arc<tokio::sync::mpsc::Sender<RgbaImage>>, // Tokio mpsc channel for async communication
     pub is_just_stopped: bool,
     socket: Arc<Mutex<Option<CasterSocket>>>,
+    codec_settings: CodecSettings,
+    target_fps: u32,
+    measured_fps: Arc<AtomicU32>, // Frame rate reale, aggiornato dal task di cattura
+    output_scaling: OutputScaling,
+    join_requests: Arc<std::sync::RwLock<Vec<JoinRequest>>>, // Condivise con la socket
 }
 
 impl AppController {
@@ -31,13 +40,76 @@ impl AppController {
             is_just_stopped: false,
             socket: Arc::new(Mutex::new(socket)),
             blanking_flag: Arc::new(AtomicBool::new(false)),
+            codec_settings: CodecSettings::default(),
+            target_fps: DEFAULT_TARGET_FPS,
+            measured_fps: Arc::new(AtomicU32::new(0)),
+            output_scaling: OutputScaling::default(),
+            join_requests: Arc::new(std::sync::RwLock::new(Vec::new())),
         }
     }
 
     pub fn set_socket(&mut self, socket: CasterSocket) {
+        self.join_requests = socket.join_requests();
         self.socket = Arc::new(Mutex::new(Some(socket)));
     }
 
+    pub fn set_codec_settings(&mut self, codec_settings: CodecSettings) {
+        self.codec_settings = codec_settings;
+    }
+
+    pub fn set_target_fps(&mut self, target_fps: u32) {
+        self.target_fps = target_fps;
+    }
+
+    pub fn set_output_scaling(&mut self, output_scaling: OutputScaling) {
+        self.output_scaling = output_scaling;
+    }
+
+    pub fn measured_fps(&self) -> u32 {
+        self.measured_fps.load(Ordering::Relaxed)
+    }
+
+    /// Richieste di registrazione in attesa di approvazione.
+    pub fn join_requests(&self) -> Vec<JoinRequest> {
+        self.join_requests.read().unwrap().clone()
+    }
+
+    pub fn approve_receiver(&self, address: String) {
+        let socket = self.socket.clone();
+        tokio::spawn(async move {
+            if let Some(sock) = socket.lock().await.as_ref() {
+                sock.approve(&address).await;
+            }
+        });
+    }
+
+    pub fn deny_receiver(&self, address: String) {
+        let socket = self.socket.clone();
+        tokio::spawn(async move {
+            if let Some(sock) = socket.lock().await.as_ref() {
+                sock.deny(&address).await;
+            }
+        });
+    }
+
+    pub fn kick_receiver(&self, address: String) {
+        let socket = self.socket.clone();
+        tokio::spawn(async move {
+            if let Some(sock) = socket.lock().await.as_ref() {
+                sock.kick(&address).await;
+            }
+        });
+    }
+
+    pub fn ban_receiver(&self, address: String) {
+        let socket = self.socket.clone();
+        tokio::spawn(async move {
+            if let Some(sock) = socket.lock().await.as_ref() {
+                sock.ban(&address).await;
+            }
+        });
+    }
+
     // Function to start screen sharing using Tokio async task
     pub fn start_sharing(&mut self) {
         self.stop_flag.store(false, Ordering::Relaxed);
@@ -47,10 +119,26 @@ impl AppController {
         let sender = self.sender.clone();
         let socket = self.socket.clone();
         let blanking_flag = Arc::clone(&self.blanking_flag);
+        let codec_settings = self.codec_settings;
+        let target_fps = self.target_fps;
+        let measured_fps = Arc::clone(&self.measured_fps);
+        let output_scaling = self.output_scaling;
 
+        self.notify_status();
         // Spawn a Tokio async task for screen sharing
         let task = tokio::spawn(async move {
-            start_screen_sharing(monitor, stop_flag, sender, socket, blanking_flag).await;
+            start_screen_sharing(
+                monitor,
+                stop_flag,
+                sender,
+                socket,
+                blanking_flag,
+                codec_settings,
+                target_fps,
+                measured_fps,
+                output_scaling,
+            )
+            .await;
         });
 
         self.set_task(task);
@@ -63,10 +151,28 @@ impl AppController {
         let stop_flag = Arc::clone(&self.stop_flag);
         let send = self.sender.clone();
         let socket = self.socket.clone();
+        let blanking_flag = Arc::clone(&self.blanking_flag);
+        let codec_settings = self.codec_settings;
+        let target_fps = self.target_fps;
+        let measured_fps = Arc::clone(&self.measured_fps);
+        let output_scaling = self.output_scaling;
+        self.notify_status();
         // Crea un nuovo thread per lo screen sharing
         let task = tokio::spawn(async move {
             // Passiamo stdin e altri dati al thread
-            start_partial_sharing(monitor, stop_flag, send, dimensions, socket).await;
+            start_partial_sharing(
+                monitor,
+                stop_flag,
+                send,
+                dimensions,
+                socket,
+                blanking_flag,
+                codec_settings,
+                target_fps,
+                measured_fps,
+                output_scaling,
+            )
+            .await;
         });
         self.set_task(task);
     }
@@ -82,14 +188,14 @@ impl AppController {
 
     // Stop streaming, async-safe
     pub fn close_streaming(&mut self) {
-        if self.stop_flag.load(Ordering::Relaxed) {
-            return;
-        }
         // Set the flag to stop streaming
         self.stop_flag.store(true, Ordering::Relaxed);
 
-        // Distruggi la socket, se presente
-        if let Some(socket) = self.socket.blocking_lock().as_mut() {
+        // Distruggi la socket, se presente: anche a trasmissione in pausa la sessione va chiusa
+        if let Some(mut socket) = self.socket.blocking_lock().take() {
+            // Avvisa i receiver: il clone tiene aperto il trasporto fino all'invio
+            let notifier = socket.clone();
+            tokio::spawn(async move { notifier.set_status(StreamStatus::Ended).await });
             //println!("Socket distrutta");
             socket.destroy();
         }
@@ -102,7 +208,8 @@ impl AppController {
             return;
         }
         // Set the flag to stop streaming
-        self.stop_flag.store(true, Ordering::Relaxed)
+        self.stop_flag.store(true, Ordering::Relaxed);
+        self.notify_status();
     }
 
     pub fn blanking_streaming(&mut self) {
@@ -111,6 +218,27 @@ impl AppController {
         } else {
             self.blanking_flag.store(true, Ordering::Relaxed)
         }
+        self.notify_status();
+    }
+
+    // Comunica ai receiver lo stato della trasmissione, letto dai flag al momento dell'invio
+    fn notify_status(&self) {
+        let socket = self.socket.clone();
+        let stop_flag = Arc::clone(&self.stop_flag);
+        let blanking_flag = Arc::clone(&self.blanking_flag);
+        tokio::spawn(async move {
+            let sock_lock = socket.lock().await;
+            if let Some(sock) = sock_lock.as_ref() {
+                let status = if stop_flag.load(Ordering::Relaxed) {
+                    StreamStatus::Paused
+                } else if blanking_flag.load(Ordering::Relaxed) {
+                    StreamStatus::Blanked
+                } else {
+                    StreamStatus::Live
+                };
+                sock.set_status(status).await;
+            }
+        });
     }
 
     pub fn take_screenshot(&mut self) -> RgbaImage {
@@ -1,15 +1,20 @@
 use crate::screenshare::screenshare::start_screen_receiving;
-use crate::socket::socket::{ReceiverSocket, RegistrationError};
+use crate::socket::reassembly::ReceiverStats;
+use crate::socket::socket::{ReceiverSocket, RegistrationError, StreamInfo, StreamStatus};
 use rand::{thread_rng, Rng};
+use std::future::Future;
 use std::process::Command;
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
+use std::sync::{Arc, RwLock};
 use std::{env, fs, thread};
 use tokio::runtime::Runtime;
 use tokio::sync::{mpsc::Sender, Mutex};
 use tokio::task;
 use xcap::image::RgbaImage;
 
+/// Tentativi di registrazione prima di rinunciare.
+const REGISTRATION_ATTEMPTS: usize = 3;
+
 pub struct ReceiverController {
     pub streaming_handle: Option<task::JoinHandle<()>>,
     stop_flag: Arc<AtomicBool>,
@@ -17,10 +22,14 @@ pub struct ReceiverController {
     socket: Arc<Mutex<ReceiverSocket>>,
     pub is_recording: Arc<AtomicBool>,
     counter: Arc<Mutex<usize>>,
+    stats: Arc<ReceiverStats>,
+    status: Arc<RwLock<StreamStatus>>,
 }
 
 impl ReceiverController {
     pub fn new(sender: Sender<RgbaImage>, socket: ReceiverSocket) -> Self {
+        let stats = socket.stats();
+        let status = socket.status();
         ReceiverController {
             streaming_handle: None,
             stop_flag: Arc::new(AtomicBool::new(false)),
@@ -28,6 +37,8 @@ impl ReceiverController {
             socket: Arc::new(Mutex::new(socket)),
             is_recording: Arc::new(AtomicBool::new(false)),
             counter: Arc::new(Mutex::new(0)),
+            stats,
+            status,
         }
     }
 
@@ -44,26 +55,31 @@ impl ReceiverController {
         self.set_handle(Some(handle));
     }
 
-    pub fn register(&self) -> Result<String, String> {
-        let mut sock_lock = self.socket.blocking_lock();
-        let rt = Runtime::new().unwrap();
-        match rt.block_on(sock_lock.register_with_caster()) {
-            Ok(_) => {
-                //println!("Ho inviato la richiesta di registrazione!");
-                Ok("Registrazione completata con successo!".to_string())
+    /// Registrazione presso il caster, da eseguire fuori dal thread dell'interfaccia:
+    /// se il caster deve approvare la richiesta l'attesa può durare a lungo.
+    pub fn register(&self) -> impl Future<Output = Result<StreamInfo, String>> + Send + 'static {
+        let socket = self.socket.clone();
+        async move {
+            let mut sock_lock = socket.lock().await;
+            // La richiesta viaggia su UDP e può andare persa: ritenta se il caster non risponde
+            let mut result = sock_lock.register_with_caster().await;
+            for _ in 1..REGISTRATION_ATTEMPTS {
+                if !matches!(result, Err(RegistrationError::Timeout)) {
+                    break;
+                }
+                result = sock_lock.register_with_caster().await;
             }
-            Err(e) => {
-                sock_lock.destroy();
-                let user_message = match e {
-                    RegistrationError::InvalidIp => "L'indirizzo IP inserito non è valido.",
-                    RegistrationError::PortParsingError => "La porta specificata non è valida.",
-                    RegistrationError::SocketNotInitialized => "La socket non è stata inizializzata correttamente.",
-                    RegistrationError::ConnectionReset => "Connessione interrotta dal caster.",
-                    RegistrationError::NetworkUnreachable => "La rete non è raggiungibile. Controlla la tua connessione.",
-                    RegistrationError::UnknownError(err) => &format!("{}", err),
-                };
-                println!("Errore durante la registrazione: {}", user_message);
-                Err(user_message.to_string())
+            match result {
+                Ok(stream_info) => {
+                    //println!("Ho inviato la richiesta di registrazione!");
+                    Ok(stream_info)
+                }
+                Err(e) => {
+                    sock_lock.destroy();
+                    let user_message = registration_error_message(e);
+                    println!("Errore durante la registrazione: {}", user_message);
+                    Err(user_message)
+                }
             }
         }
     }
@@ -75,6 +91,27 @@ impl ReceiverController {
         //println!("Ho inviato la richiesta di disconessione!");
     }
 
+    pub fn dropped_frames(&self) -> u64 {
+        self.stats.frames_dropped()
+    }
+
+    pub fn recovered_chunks(&self) -> u64 {
+        self.stats.chunks_recovered()
+    }
+
+    pub fn rejected_packets(&self) -> u64 {
+        self.stats.packets_rejected()
+    }
+
+    pub fn caster_fps(&self) -> u32 {
+        self.stats.caster_fps()
+    }
+
+    /// Ultimo stato della trasmissione annunciato dal caster.
+    pub fn stream_status(&self) -> StreamStatus {
+        *self.status.read().unwrap()
+    }
+
     pub fn set_handle(&mut self, handle: Option<task::JoinHandle<()>>) {
         self.streaming_handle = handle;
     }
@@ -139,7 +176,12 @@ impl ReceiverController {
 
     pub fn stop_recording(&self) {
         if self.is_recording.load(Ordering::Relaxed) {
-            let actual_fps = "8"; // Sostituisci con il frame rate effettivo
+            // Frame rate comunicato dal caster, 8 se non ancora noto
+            let actual_fps = match self.stats.caster_fps() {
+                0 => 8,
+                fps => fps,
+            }
+            .to_string();
             let input_pattern = "monitors-%d.png";
             let counter = Arc::clone(&self.counter);
             let mut counter_guard = counter.blocking_lock(); // Clona il contatore
@@ -150,7 +192,7 @@ impl ReceiverController {
             let status = Command::new("ffmpeg")
                 .current_dir(working_dir) // Imposta la directory di lavoro
                 .arg("-framerate")
-                .arg(actual_fps)
+                .arg(&actual_fps)
                 .arg("-i")
                 .arg(input_pattern)
                 .arg("-c:v")
@@ -252,3 +294,22 @@ impl ReceiverController {
         format!("video-{}.mp4", random_number)
     }
 }
+
+/// Messaggio mostrato all'utente quando la registrazione (o la risoluzione dell'indirizzo) fallisce.
+pub fn registration_error_message(e: RegistrationError) -> String {
+    match e {
+        RegistrationError::InvalidIp => "L'indirizzo IP inserito non è valido.".to_string(),
+        RegistrationError::PortParsingError => "La porta specificata non è valida.".to_string(),
+        RegistrationError::UnresolvedHost(host) => format!("Impossibile risolvere il nome host {}.", host),
+        RegistrationError::SocketNotInitialized => "La socket non è stata inizializzata correttamente.".to_string(),
+        RegistrationError::ConnectionReset => "Connessione interrotta dal caster.".to_string(),
+        RegistrationError::ConnectionRefused => "Il caster ha rifiutato la connessione. Controlla il trasporto scelto.".to_string(),
+        RegistrationError::Timeout => "Nessuna risposta dal caster. Controlla l'indirizzo e che la trasmissione sia avviata.".to_string(),
+        RegistrationError::ApprovalTimeout => "Il caster non ha approvato la richiesta in tempo.".to_string(),
+        RegistrationError::Rejected(reason) => format!("Il caster ha rifiutato la registrazione: {}", reason),
+        RegistrationError::UnexpectedReply => "Risposta inattesa dal caster. Riprova.".to_string(),
+        RegistrationError::InvalidPassphrase => "PIN o passphrase della sessione errati.".to_string(),
+        RegistrationError::NetworkUnreachable => "La rete non è raggiungibile. Controlla la tua connessione.".to_string(),
+        RegistrationError::UnknownError(err) => err,
+    }
+}
@@ -1,17 +1,21 @@
 use super::component::caster_streaming;
 use super::component::AnnotationToolsComponent::MessageAnnotation;
+use crate::codec::video::CodecSettings;
 use crate::controller::app_controller::AppController;
-use crate::controller::receiver_controller::ReceiverController;
+use crate::controller::receiver_controller::{registration_error_message, ReceiverController};
 use crate::gui::component::caster_settings;
 use crate::gui::component::caster_settings::CasterSettings;
 use crate::gui::component::caster_streaming::{CasterStreaming, MessageUpdate};
+use crate::gui::component::connection;
 use crate::gui::component::connection::Connection;
 use crate::gui::component::home::Home;
 use crate::gui::component::home::Role;
 use crate::gui::component::receiver_ip;
 use crate::gui::component::receiver_ip::ReceiverIp;
 use crate::gui::component::receiver_streaming;
-use crate::gui::component::receiver_streaming::{ReceiverStreaming, UpdateMessage};
+use crate::gui::component::receiver_streaming::{
+    BlankPlaceholder, ReceiverStreaming, UpdateMessage,
+};
 use crate::gui::component::shorcut::{Shortcut, ShortcutMessage, Shortcuts};
 use crate::gui::component::window_part_screen::{MessagePress, WindowPartScreen};
 use crate::gui::component::{home, Component};
@@ -27,7 +31,18 @@ use crate::gui::component::AnnotationToolsComponent::AnnotationTools;
 use crate::gui::theme::container::Style;
 use crate::gui::theme::Theme;
 use crate::model::shortcut::{from_key_to_string, ShortcutController};
-use crate::socket::socket::{CasterSocket, ReceiverSocket};
+use crate::screenshare::scaling::OutputScaling;
+use crate::screenshare::screenshare::DEFAULT_TARGET_FPS;
+use crate::socket::compression::Compression;
+use crate::socket::discovery::{DiscoveryBrowser, DISCOVERY_GROUP};
+use crate::socket::fec::Redundancy;
+use crate::socket::pacing::MaxBandwidth;
+use crate::socket::session_uri::SessionUri;
+use crate::socket::transport::TransportKind;
+use crate::socket::socket::{
+    receiver_bind_address, resolve_caster_address, CasterSocket, ReceiverSocket, StreamInfo,
+    StreamStatus, ViewerInfo,
+};
 use crate::utils::utils::get_screen_scaled;
 use iced::keyboard::Key;
 use iced::time::{self, Duration};
@@ -36,7 +51,6 @@ use iced::widget::container::Appearance;
 use iced::window::settings::PlatformSpecific;
 use iced::window::{close, Level, Position};
 use iced::{executor, font, window, Border, Color, Command, Point, Size, Subscription};
-use local_ip_address::local_ip;
 use std::sync::atomic::Ordering;
 use std::sync::{Arc, RwLock};
 use tokio::sync::{
@@ -60,7 +74,7 @@ pub struct App {
     sender_receiver: Sender<RgbaImage>,
     shortcut_screen: Shortcut,
     shortcut_controller: ShortcutController,
-    notification_rx: Option<tokio::sync::watch::Receiver<usize>>,
+    notification_rx: Option<tokio::sync::watch::Receiver<Vec<ViewerInfo>>>,
     second_window_id: Option<window::Id>,
     third_window_id: Option<window::Id>,
     annotationTools: AnnotationTools,
@@ -99,12 +113,20 @@ pub enum Message {
     RoleChosen(home::Message),
     ReceiverSharing(String),
     ReceiverInputIp(receiver_ip::Message),
+    ConnectionInput(connection::Message),
     SetSettingsCaster(caster_settings::Window),
     Back(Page),
     StartRecording(receiver_streaming::UpdateMessage),
+    UpdateReceiverStreaming(receiver_streaming::UpdateMessage),
     TogglerChanged(caster_streaming::MessageUpdate),
     KeyShortcut(Key),
     SelectDisplay(Monitor),
+    SetCodecSettings(CodecSettings),
+    SetTargetFps(u32),
+    SetOutputScaling(OutputScaling),
+    SetTransport(TransportKind),
+    SetMaxBandwidth(MaxBandwidth),
+    SetRedundancy(Redundancy),
     Close,
     UpdateScreen,
     StartPartialSharing(f32, f32, f64, f64),
@@ -114,7 +136,16 @@ pub enum Message {
     StopStreaming,
     None,
     SetCasterSocket(CasterSocket, Page, Modality),
+    CasterSocketFailed(String),
+    OpenUri(SessionUri),
     ReceiverControllerCreated(ReceiverSocket, Sender<RgbaImage>, Page),
+    ReceiverSocketFailed(String),
+    ReceiverRegistered(Result<StreamInfo, String>, Page),
+    ApproveReceiver(String),
+    DenyReceiver(String),
+    ToggleViewers,
+    KickReceiver(String),
+    BanReceiver(String),
     ChosenShortcuts(Shortcuts),
     Blanking,
     PendingOne(Pending),
@@ -138,9 +169,9 @@ impl Application for App {
     type Message = Message;
     type Theme = Theme;
 
-    type Flags = ();
+    type Flags = Option<SessionUri>; // URI screencast:// passato sulla riga di comando
 
-    fn new(_flags: Self::Flags) -> (Self, iced::Command<Self::Message>) {
+    fn new(flags: Self::Flags) -> (Self, iced::Command<Self::Message>) {
         let (sender_caster, receiver_caster) = channel::<RgbaImage>(32); // Define buffer size
         let (sender_receiver, receiver_receiver) = channel::<RgbaImage>(32); // Define buffer size
 
@@ -154,30 +185,59 @@ impl Application for App {
                 current_page: Page::Home,
                 home: Home {},
                 connection: Connection {
-                    ip_address: "".to_string(),
+                    ip_address: None,
+                    interfaces: Vec::new(),
+                    port: "".to_string(),
+                    message: "".to_string(),
+                    passphrase: "".to_string(),
+                    require_approval: false,
+                    transport: TransportKind::default(),
+                    qr_code: None,
                 },
                 receiver_ip: ReceiverIp {
                     indirizzo_ip: "".to_string(),
                     message: "".to_string(),
+                    transport: TransportKind::default(),
+                    passphrase: "".to_string(),
+                    display_name: "".to_string(),
+                    session: None,
+                    discovery: None,
                 },
                 receiver_streaming: ReceiverStreaming {
                     recording: false,
                     receiver: Arc::new(Mutex::new(receiver_receiver)),
                     frame_to_update: Arc::new(Mutex::new(None)),
                     is_loading: true,
+                    dropped_frames: 0,
+                    recovered_chunks: 0,
+                    rejected_packets: 0,
+                    caster_fps: 0,
+                    stream_info: None,
+                    status: StreamStatus::default(),
+                    placeholder: BlankPlaceholder::default(),
+                    placeholder_message: "".to_string(),
                 },
                 caster_settings: CasterSettings {
                     available_displays: Monitor::all().unwrap(),
                     selected_display: Monitor::all().unwrap().get(0).unwrap().clone(),
+                    codec_settings: CodecSettings::default(),
+                    target_fps: DEFAULT_TARGET_FPS,
+                    output_scaling: OutputScaling::default(),
+                    transport: TransportKind::default(),
+                    max_bandwidth: MaxBandwidth::default(),
+                    redundancy: Redundancy::default(),
                 },
                 caster_streaming: CasterStreaming {
                     toggler: false,
                     receiver: Arc::new(Mutex::new(receiver_caster)),
                     frame_to_update: Arc::new(Mutex::new(None)),
                     warning_message: false,
-                    viewrs: Arc::new(RwLock::new(0)),
+                    viewrs: Arc::new(RwLock::new(Vec::new())),
+                    show_viewers: false,
                     modality: Modality::Full,
                     stop: false,
+                    fps: 0,
+                    join_requests: Vec::new(),
                 },
                 windows_part_screen: WindowPartScreen {
                     screenshot: None,
@@ -224,6 +284,10 @@ impl Application for App {
                     .map(Message::FontLoaded),
                 font::load(include_bytes!("../../resources/Barlow-Bold.ttf").as_slice())
                     .map(Message::FontLoaded),
+                match flags {
+                    Some(uri) => Command::perform(async {}, move |_| Message::OpenUri(uri)),
+                    None => Command::none(),
+                },
             ]),
         )
     }
@@ -328,15 +392,27 @@ impl Application for App {
             Message::RoleChosen(role) => match role {
                 home::Message::ChosenRole(role) => match role {
                     Role::Caster => {
-                        self.controller = Controller::CasterController(AppController::new(
+                        let mut caster = AppController::new(
                             Monitor::all().unwrap().get(0).unwrap().clone(),
                             self.sender_caster.clone(),
                             None,
-                        ));
+                        );
+                        caster.set_codec_settings(self.caster_settings.codec_settings);
+                        caster.set_target_fps(self.caster_settings.target_fps);
+                        caster.set_output_scaling(self.caster_settings.output_scaling);
+                        self.controller = Controller::CasterController(caster);
                         self.current_page = Page::CasterSettings;
                         Command::none()
                     }
                     Role::Receiver => {
+                        // Cerca i caster annunciati sulla rete locale finché la pagina resta aperta
+                        self.receiver_ip.discovery = match DiscoveryBrowser::start(DISCOVERY_GROUP) {
+                            Ok(browser) => Some(browser),
+                            Err(e) => {
+                                eprintln!("Discovery dei caster non disponibile: {}", e);
+                                None
+                            }
+                        };
                         self.current_page = Page::ReceiverIp;
                         Command::none()
                     }
@@ -348,23 +424,43 @@ impl Application for App {
             }
             Message::StartSharing => {
                 //devo creare solo la socket
-                let (notification_tx, notification_rx) = tokio::sync::watch::channel(0);
+                let bind_address = match self.connection.bind_address() {
+                    Ok(bind_address) => bind_address,
+                    Err(message) => {
+                        self.connection.message = message;
+                        return Command::none();
+                    }
+                };
+                let (notification_tx, notification_rx) = tokio::sync::watch::channel(Vec::new());
                 self.notification_rx = Some(notification_rx);
+                let transport = self.caster_settings.transport;
+                let max_bandwidth = self.caster_settings.max_bandwidth;
+                let redundancy = self.caster_settings.redundancy;
+                let access = self.connection.access_policy();
                 Command::perform(
                     async move {
                         //println!("Creata nuova socket caster");
-                        let caster_ip = local_ip().unwrap();
-                        //println!("{:?}", caster_ip);
                         let socket = crate::socket::socket::CasterSocket::new(
-                            &format!("{}:7878", caster_ip),
+                            &bind_address.to_string(),
                             notification_tx,
+                            Compression::default(),
+                            transport,
+                            access,
                         )
-                        .await;
+                        .await
+                        .map_err(|e| format!("Cannot listen on {}: {}", bind_address, e));
+                        if let Ok(socket) = &socket {
+                            socket.set_max_bandwidth(max_bandwidth);
+                            socket.set_redundancy(redundancy);
+                        }
 
                         let page = Page::CasterStreaming;
                         (socket, page)
                     },
-                    move |(socket, page)| Message::SetCasterSocket(socket, page, Modality::Full),
+                    move |(socket, page)| match socket {
+                        Ok(socket) => Message::SetCasterSocket(socket, page, Modality::Full),
+                        Err(message) => Message::CasterSocketFailed(message),
+                    },
                 )
             }
             Message::ReceiverSharing(ip_caster) => {
@@ -373,54 +469,79 @@ impl Application for App {
                 if let Controller::NotDefined = &mut self.controller {
                     //println!("bottone cliccato 2");
                     let sender = self.sender_receiver.clone();
-                    let mut rng = rand::thread_rng();
-                    let random_digit: u8 = rand::Rng::gen_range(&mut rng, 0..8);
+                    let transport = self.receiver_ip.transport;
+                    let passphrase = self.receiver_ip.passphrase();
+                    let display_name = self.receiver_ip.display_name();
                     Command::perform(
                         async move {
-                            let receiver_ip = local_ip().unwrap();
-                            //println!("{:?}", receiver_ip);
-                            let socket = crate::socket::socket::ReceiverSocket::new(
-                                &format!("{}:787{}", receiver_ip, random_digit),
-                                &format!("{}:7878", ip_caster),
+                            let caster_address = resolve_caster_address(&ip_caster)
+                                .await
+                                .map_err(registration_error_message)?;
+                            // Porta scelta dal sistema: più receiver possono girare sullo stesso host
+                            let receiver_address = receiver_bind_address(caster_address);
+                            //println!("{:?}", receiver_address);
+                            let mut socket = crate::socket::socket::ReceiverSocket::new(
+                                &receiver_address.to_string(),
+                                &caster_address.to_string(),
+                                transport,
                             )
-                            .await;
+                            .await
+                            .map_err(|e| format!("Cannot open the socket: {}", e))?;
+                            socket.set_passphrase(passphrase);
+                            socket.set_display_name(display_name);
                             let page = Page::ReceiverStreaming;
                             //println!("NAMO");
-                            (socket, sender, page)
+                            Ok((socket, sender, page))
                         },
-                        move |(socket, sender, page)| {
+                        move |result: Result<_, String>| match result {
                             // Once the operation is complete, send a "ControllerCreated" message
-                            //self.controller = caster_controller;
-                            //println!("NAMO 2");
-                            Message::ReceiverControllerCreated(socket, sender, page)
+                            Ok((socket, sender, page)) => {
+                                Message::ReceiverControllerCreated(socket, sender, page)
+                            }
+                            Err(message) => Message::ReceiverSocketFailed(message),
                         },
                     )
                 } else {
-                    if let Controller::ReceiverController(receiver) = &mut self.controller {
-                        //println!("bottone cliccato 2");
-                        match receiver.register() {
-                            Ok(_) => {
-                                //println!("cambio pagina");
-                                self.current_page = Page::ReceiverStreaming;
-                                receiver.start_receiving();
-                            }
-                            Err(message) => {
-                                self.receiver_ip.message = message;
-                                self.controller = Controller::NotDefined;
-                            }
-                        }
-                    }
+                    // Registrazione già in corso: si attende la risposta del caster
                     Command::none()
                 }
             }
             Message::ReceiverControllerCreated(socket, sender, page) => {
-                self.controller =
-                    Controller::ReceiverController(ReceiverController::new(sender, socket));
+                let receiver = ReceiverController::new(sender, socket);
+                let registration = receiver.register();
+                self.controller = Controller::ReceiverController(receiver);
+                self.receiver_ip.message = "Waiting for the caster...".to_string();
+                Command::perform(registration, move |result| {
+                    Message::ReceiverRegistered(result, page)
+                })
+            }
+            Message::OpenUri(uri) => {
+                // Connessione diretta alla sessione indicata dall'URI
+                self.receiver_ip.apply_uri(uri);
+                self.current_page = Page::ReceiverIp;
+                self.update(Message::ReceiverSharing(self.receiver_ip.indirizzo_ip.clone()))
+            }
+            Message::ReceiverSocketFailed(message) => {
+                self.receiver_ip.message = message;
+                Command::none()
+            }
+            Message::CasterSocketFailed(message) => {
+                eprintln!("{}", message);
+                self.connection.message = message;
+                Command::none()
+            }
+            Message::ReceiverRegistered(result, page) => {
+                self.receiver_ip.message = "".to_string();
+                // L'utente può essere tornato indietro durante l'attesa
                 if let Controller::ReceiverController(receiver) = &mut self.controller {
-                    match receiver.register() {
-                        Ok(_) => {
+                    match result {
+                        Ok(stream_info) => {
+                            self.receiver_ip.discovery = None;
                             self.current_page = page;
                             receiver.start_receiving();
+                            let _ = self
+                                .receiver_streaming
+                                .update(UpdateMessage::Connected(stream_info));
                         }
                         Err(message) => {
                             self.receiver_ip.message = message;
@@ -436,6 +557,7 @@ impl Application for App {
                     Page::Connection => Page::CasterSettings,
                     Page::ReceiverIp => {
                         self.receiver_ip.message = "".to_string();
+                        self.receiver_ip.discovery = None;
                         Page::Home
                     }
                     Page::ReceiverStreaming => {
@@ -462,9 +584,13 @@ impl Application for App {
                 let _ = self.receiver_ip.update(message);
                 Command::none()
             }
+            Message::ConnectionInput(message) => {
+                let _ = self.connection.update(message);
+                Command::none()
+            }
             Message::SetSettingsCaster(message) => {
-                let caster_ip = local_ip().unwrap();
-                self.connection.ip_address = caster_ip.to_string();
+                self.connection.refresh_interfaces(self.caster_settings.transport);
+                self.connection.message = "".to_string();
                 match message {
                     caster_settings::Window::FullScreen => {
                         self.current_page = Page::Connection;
@@ -504,6 +630,10 @@ impl Application for App {
                     }
                 }
             }
+            Message::UpdateReceiverStreaming(message) => {
+                let _ = self.receiver_streaming.update(message);
+                Command::none()
+            }
             Message::TogglerChanged(message) => match self.second_window_id {
                 None => {
                     let (second_window_id, command) = window::spawn::<Message>(window::Settings {
@@ -584,6 +714,34 @@ impl Application for App {
                 }
                 Command::none()
             }
+            Message::ApproveReceiver(address) => {
+                if let Controller::CasterController(caster) = &self.controller {
+                    caster.approve_receiver(address);
+                }
+                Command::none()
+            }
+            Message::DenyReceiver(address) => {
+                if let Controller::CasterController(caster) = &self.controller {
+                    caster.deny_receiver(address);
+                }
+                Command::none()
+            }
+            Message::ToggleViewers => {
+                let _ = self.caster_streaming.update(MessageUpdate::ToggleViewers);
+                Command::none()
+            }
+            Message::KickReceiver(address) => {
+                if let Controller::CasterController(caster) = &self.controller {
+                    caster.kick_receiver(address);
+                }
+                Command::none()
+            }
+            Message::BanReceiver(address) => {
+                if let Controller::CasterController(caster) = &self.controller {
+                    caster.ban_receiver(address);
+                }
+                Command::none()
+            }
             Message::Blanking => {
                 if let Controller::CasterController(caster) = &mut self.controller {
                     self.caster_streaming.warning_message = !self.caster_streaming.warning_message;
@@ -605,6 +763,51 @@ impl Application for App {
                     .update(caster_settings::Message::SelectDisplay(display));
                 Command::none()
             }
+            Message::SetCodecSettings(codec_settings) => {
+                if let Controller::CasterController(caster) = &mut self.controller {
+                    caster.set_codec_settings(codec_settings);
+                }
+                let _ = self
+                    .caster_settings
+                    .update(caster_settings::Message::SelectCodec(codec_settings));
+                Command::none()
+            }
+            Message::SetTargetFps(fps) => {
+                if let Controller::CasterController(caster) = &mut self.controller {
+                    caster.set_target_fps(fps);
+                }
+                let _ = self
+                    .caster_settings
+                    .update(caster_settings::Message::SelectFps(fps));
+                Command::none()
+            }
+            Message::SetOutputScaling(output_scaling) => {
+                if let Controller::CasterController(caster) = &mut self.controller {
+                    caster.set_output_scaling(output_scaling);
+                }
+                let _ = self
+                    .caster_settings
+                    .update(caster_settings::Message::SelectScaling(output_scaling));
+                Command::none()
+            }
+            Message::SetTransport(transport) => {
+                let _ = self
+                    .caster_settings
+                    .update(caster_settings::Message::SelectTransport(transport));
+                Command::none()
+            }
+            Message::SetMaxBandwidth(max_bandwidth) => {
+                let _ = self
+                    .caster_settings
+                    .update(caster_settings::Message::SelectMaxBandwidth(max_bandwidth));
+                Command::none()
+            }
+            Message::SetRedundancy(redundancy) => {
+                let _ = self
+                    .caster_settings
+                    .update(caster_settings::Message::SelectRedundancy(redundancy));
+                Command::none()
+            }
             Message::Close => {
                 if let Controller::CasterController(caster) = &mut self.controller {
                     caster.close_streaming();
@@ -621,33 +824,58 @@ impl Application for App {
                 Command::none()
             }
             Message::UpdateScreen => {
+                // Il caster ha chiuso la sessione: si torna alla home come dopo una chiusura
+                if let Controller::ReceiverController(controller) = &self.controller {
+                    if controller.stream_status() == StreamStatus::Ended {
+                        return self.update(Message::Close);
+                    }
+                }
                 match &self.controller {
                     Controller::ReceiverController(controller) => {
+                        let _ = self.receiver_streaming.update(UpdateMessage::Stats {
+                            dropped_frames: controller.dropped_frames(),
+                            recovered_chunks: controller.recovered_chunks(),
+                            rejected_packets: controller.rejected_packets(),
+                            caster_fps: controller.caster_fps(),
+                        });
+                        let _ = self
+                            .receiver_streaming
+                            .update(UpdateMessage::Status(controller.stream_status()));
+                        // Svuota il canale: ogni frame viene registrato, solo l'ultimo mostrato
                         let frame = {
-                            if let Ok(receiver) =
-                                self.receiver_streaming.receiver.blocking_lock().try_recv()
-                            {
-                                receiver
-                            } else {
-                                return Command::none();
+                            let mut receiver = self.receiver_streaming.receiver.blocking_lock();
+                            let mut latest = None;
+                            while let Ok(frame) = receiver.try_recv() {
+                                controller.start_recording(frame.clone());
+                                latest = Some(frame);
+                            }
+                            match latest {
+                                Some(frame) => frame,
+                                None => return Command::none(),
                             }
                         };
-                        // if self.receiver_streaming.recording {
-                        controller.start_recording(frame.clone());
-                        //}
                         let _ = self
                             .receiver_streaming
                             .update(UpdateMessage::NewFrame(frame));
                     }
 
-                    Controller::CasterController(_) => {
+                    Controller::CasterController(caster) => {
+                        let _ = self
+                            .caster_streaming
+                            .update(MessageUpdate::Fps(caster.measured_fps()));
+                        let _ = self
+                            .caster_streaming
+                            .update(MessageUpdate::JoinRequests(caster.join_requests()));
+                        // Mostra solo l'ultimo frame, così la cattura non resta in attesa della UI
                         let frame = {
-                            if let Ok(receiver) =
-                                self.caster_streaming.receiver.blocking_lock().try_recv()
-                            {
-                                receiver
-                            } else {
-                                return Command::none();
+                            let mut receiver = self.caster_streaming.receiver.blocking_lock();
+                            let mut latest = None;
+                            while let Ok(frame) = receiver.try_recv() {
+                                latest = Some(frame);
+                            }
+                            match latest {
+                                Some(frame) => frame,
+                                None => return Command::none(),
                             }
                         };
                         let _ = self.caster_streaming.update(MessageUpdate::NewFrame(frame));
@@ -657,29 +885,48 @@ impl Application for App {
                 Command::none()
             }
             Message::StartPartialSharing(x, y, start_x, start_y) => {
-                let (notification_tx, notification_rx) = tokio::sync::watch::channel(0);
+                let bind_address = match self.connection.bind_address() {
+                    Ok(bind_address) => bind_address,
+                    Err(message) => {
+                        eprintln!("{}", message);
+                        self.connection.message = message;
+                        return Command::none();
+                    }
+                };
+                let (notification_tx, notification_rx) = tokio::sync::watch::channel(Vec::new());
                 self.notification_rx = Some(notification_rx);
+                let transport = self.caster_settings.transport;
+                let max_bandwidth = self.caster_settings.max_bandwidth;
+                let redundancy = self.caster_settings.redundancy;
+                let access = self.connection.access_policy();
                 //creo la caster socket
                 Command::perform(
                     async move {
                         //println!("Creata nuova socket caster");
-                        let caster_ip = local_ip().unwrap();
-                        //println!("{:?}", caster_ip);
                         let socket = crate::socket::socket::CasterSocket::new(
-                            &format!("{}:7878", caster_ip),
+                            &bind_address.to_string(),
                             notification_tx,
+                            Compression::default(),
+                            transport,
+                            access,
                         )
-                        .await;
+                        .await
+                        .map_err(|e| format!("Cannot listen on {}: {}", bind_address, e));
+                        if let Ok(socket) = &socket {
+                            socket.set_max_bandwidth(max_bandwidth);
+                            socket.set_redundancy(redundancy);
+                        }
 
                         let page = Page::CasterStreaming;
                         (socket, page)
                     },
-                    move |(socket, page)| {
-                        Message::SetCasterSocket(
+                    move |(socket, page)| match socket {
+                        Ok(socket) => Message::SetCasterSocket(
                             socket,
                             page,
                             Modality::Partial(x, y, start_x, start_y),
-                        )
+                        ),
+                        Err(message) => Message::CasterSocketFailed(message),
                     },
                 )
             }
@@ -770,7 +1017,7 @@ impl Application for App {
                                     let mut notification_rx = notification_rx; // Clona il ricevitore per usarlo nel task
                                     while notification_rx.changed().await.is_ok() {
                                         // Ricevi il valore aggiornato
-                                        let viewers = *notification_rx.borrow();
+                                        let viewers = notification_rx.borrow().clone();
                                         *viewrs_clone.write().unwrap() = viewers;
                                         /*println!(
                                             "Numero di visualizzatori aggiornato: {}",
@@ -801,7 +1048,7 @@ impl Application for App {
                                     let mut notification_rx = notification_rx; // Clona il ricevitore per usarlo nel task
                                     while notification_rx.changed().await.is_ok() {
                                         // Ricevi il valore aggiornato
-                                        let viewers = *notification_rx.borrow();
+                                        let viewers = notification_rx.borrow().clone();
                                         *viewrs_clone.write().unwrap() = viewers;
                                         /*println!(
                                             "Numero di visualizzatori aggiornato: {}",
@@ -2,18 +2,31 @@ use iced::alignment::{Horizontal, Vertical};
 use iced::widget::{container, pick_list, row};
 use iced::{Command, Length::Fill, Subscription};
 use xcap::Monitor;
+use crate::codec::video::{CodecSettings, VideoCodec};
 use crate::column_iced;
 use crate::gui::component::Component;
 use crate::gui::theme::button::circle_button::CircleButton;
 use crate::gui::theme::button::RectangleButton;
 use crate::gui::theme::button::Style;
 use crate::gui::theme::icon::Icon;
+use crate::gui::theme::text::text;
 use crate::gui::theme::widget::Element;
+use crate::screenshare::scaling::{OutputResolution, OutputScaling, ScalingFilter};
+use crate::screenshare::screenshare::TARGET_FPS_OPTIONS;
+use crate::socket::fec::Redundancy;
+use crate::socket::pacing::MaxBandwidth;
+use crate::socket::transport::TransportKind;
 use crate::gui::{app, resource};
 
 pub struct CasterSettings {
     pub available_displays: Vec<Monitor>,
     pub selected_display: Monitor,
+    pub codec_settings: CodecSettings,
+    pub target_fps: u32,
+    pub output_scaling: OutputScaling,
+    pub transport: TransportKind,
+    pub max_bandwidth: MaxBandwidth,
+    pub redundancy: Redundancy,
 }
 
 #[derive(Debug, Clone)]
@@ -26,6 +39,12 @@ pub enum Window {
 pub enum Message {
     SelectDisplay(Monitor), // Cambiare tipo nel display corrispondente
     SelectWindow(Window),                  // Probabilmente avrà bisogno di parametri
+    SelectCodec(CodecSettings),
+    SelectFps(u32),
+    SelectScaling(OutputScaling),
+    SelectTransport(TransportKind),
+    SelectMaxBandwidth(MaxBandwidth),
+    SelectRedundancy(Redundancy),
 }
 
 impl From<Message> for app::Message {
@@ -37,6 +56,24 @@ impl From<Message> for app::Message {
             Message::SelectWindow(window) => {
                 return app::Message::SetSettingsCaster(window);
             }
+            Message::SelectCodec(codec_settings) => {
+                return app::Message::SetCodecSettings(codec_settings);
+            }
+            Message::SelectFps(fps) => {
+                return app::Message::SetTargetFps(fps);
+            }
+            Message::SelectScaling(output_scaling) => {
+                return app::Message::SetOutputScaling(output_scaling);
+            }
+            Message::SelectTransport(transport) => {
+                return app::Message::SetTransport(transport);
+            }
+            Message::SelectMaxBandwidth(max_bandwidth) => {
+                return app::Message::SetMaxBandwidth(max_bandwidth);
+            }
+            Message::SelectRedundancy(redundancy) => {
+                return app::Message::SetRedundancy(redundancy);
+            }
         }
     }
 }
@@ -51,6 +88,30 @@ impl<'a> Component<'a> for CasterSettings {
                 Command::none()
             }
             Message::SelectWindow(_window) => todo!(),
+            Message::SelectCodec(codec_settings) => {
+                self.codec_settings = codec_settings;
+                Command::none()
+            }
+            Message::SelectFps(fps) => {
+                self.target_fps = fps;
+                Command::none()
+            }
+            Message::SelectScaling(output_scaling) => {
+                self.output_scaling = output_scaling;
+                Command::none()
+            }
+            Message::SelectTransport(transport) => {
+                self.transport = transport;
+                Command::none()
+            }
+            Message::SelectMaxBandwidth(max_bandwidth) => {
+                self.max_bandwidth = max_bandwidth;
+                Command::none()
+            }
+            Message::SelectRedundancy(redundancy) => {
+                self.redundancy = redundancy;
+                Command::none()
+            }
         }
     }
 
@@ -87,6 +148,74 @@ impl<'a> Component<'a> for CasterSettings {
             .font(resource::font::BARLOW)
             .width(456);
 
+        let settings = self.codec_settings;
+        let choose_codec = pick_list(
+            VideoCodec::ALL,
+            Some(settings.codec),
+            move |codec| Message::SelectCodec(CodecSettings { codec, ..settings }).into(),
+        )
+            .font(resource::font::BARLOW)
+            .width(160);
+        let choose_bitrate = pick_list(
+            CodecSettings::BITRATES_KBPS,
+            Some(settings.bitrate_kbps),
+            move |bitrate_kbps| Message::SelectCodec(CodecSettings { bitrate_kbps, ..settings }).into(),
+        )
+            .font(resource::font::BARLOW)
+            .width(100);
+        let choose_keyframe_interval = pick_list(
+            CodecSettings::KEYFRAME_INTERVALS,
+            Some(settings.keyframe_interval),
+            move |keyframe_interval| {
+                Message::SelectCodec(CodecSettings { keyframe_interval, ..settings }).into()
+            },
+        )
+            .font(resource::font::BARLOW)
+            .width(80);
+        let choose_fps = pick_list(
+            TARGET_FPS_OPTIONS,
+            Some(self.target_fps),
+            |fps| Message::SelectFps(fps).into(),
+        )
+            .font(resource::font::BARLOW)
+            .width(80);
+        let scaling = self.output_scaling;
+        let choose_resolution = pick_list(
+            OutputResolution::PRESETS,
+            Some(scaling.resolution),
+            move |resolution| Message::SelectScaling(OutputScaling { resolution, ..scaling }).into(),
+        )
+            .font(resource::font::BARLOW)
+            .width(160);
+        let choose_filter = pick_list(
+            ScalingFilter::ALL,
+            Some(scaling.filter),
+            move |filter| Message::SelectScaling(OutputScaling { filter, ..scaling }).into(),
+        )
+            .font(resource::font::BARLOW)
+            .width(140);
+        let choose_transport = pick_list(
+            TransportKind::ALL,
+            Some(self.transport),
+            |transport| Message::SelectTransport(transport).into(),
+        )
+            .font(resource::font::BARLOW)
+            .width(80);
+        let choose_max_bandwidth = pick_list(
+            MaxBandwidth::PRESETS,
+            Some(self.max_bandwidth),
+            |max_bandwidth| Message::SelectMaxBandwidth(max_bandwidth).into(),
+        )
+            .font(resource::font::BARLOW)
+            .width(120);
+        let choose_redundancy = pick_list(
+            Redundancy::PRESETS,
+            Some(self.redundancy),
+            |redundancy| Message::SelectRedundancy(redundancy).into(),
+        )
+            .font(resource::font::BARLOW)
+            .width(120);
+
         // Organizzare i pulsanti in una riga o colonna
         container(column_iced![
             back_button,
@@ -96,7 +225,30 @@ impl<'a> Component<'a> for CasterSettings {
                         .spacing(16) // Spaziatura tra i pulsanti
                         .align_items(iced::Alignment::Center),
                     row![],
-                    row![choose_screen_button].align_items(iced::Alignment::Center)
+                    row![choose_screen_button, choose_transport, text("Max bandwidth"), choose_max_bandwidth]
+                        .spacing(8)
+                        .align_items(iced::Alignment::Center),
+                    row![
+                        choose_codec,
+                        choose_bitrate,
+                        text("kbps"),
+                        text("Keyframe every"),
+                        choose_keyframe_interval,
+                        text("frames"),
+                    ]
+                    .spacing(8)
+                    .align_items(iced::Alignment::Center),
+                    row![
+                        text("Max frame rate"),
+                        choose_fps,
+                        text("fps"),
+                        text("Output"),
+                        choose_resolution,
+                        choose_filter,
+                        choose_redundancy,
+                    ]
+                    .spacing(8)
+                    .align_items(iced::Alignment::Center)
                 ]
                 .spacing(15)
             )
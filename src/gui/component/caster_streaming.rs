@@ -9,10 +9,13 @@ use iced::widget::{container, image, row, Image};
 use iced::{event, keyboard::Event::KeyPressed, Event};
 use iced::{Command, Subscription};
 use std::sync::{Arc, RwLock};
+use std::time::Instant;
 use tokio::sync::{mpsc::Receiver, Mutex};
 use xcap::image::RgbaImage;
 use crate::column_iced;
 use crate::gui::app::Modality;
+use crate::gui::theme::widget::Column;
+use crate::socket::socket::{JoinRequest, ViewerInfo};
 
 pub struct CasterStreaming {
     pub toggler: bool,
@@ -20,8 +23,11 @@ pub struct CasterStreaming {
     pub frame_to_update: Arc<Mutex<Option<RgbaImage>>>,
     pub warning_message: bool,
     pub modality: Modality,
-    pub viewrs: Arc<RwLock<usize>>,
+    pub viewrs: Arc<RwLock<Vec<ViewerInfo>>>,
+    pub show_viewers: bool, // Pannello con l'elenco dei receiver connessi
     pub stop: bool,
+    pub fps: u32,
+    pub join_requests: Vec<JoinRequest>, // Receiver in attesa di approvazione
 }
 
 #[derive(Debug, Clone)]
@@ -29,6 +35,9 @@ pub enum MessageUpdate {
     TogglerChanged(bool),
     NewFrame(RgbaImage),
     KeyPressed(Key),
+    Fps(u32),
+    JoinRequests(Vec<JoinRequest>),
+    ToggleViewers,
 }
 
 impl From<MessageUpdate> for app::Message {
@@ -40,6 +49,9 @@ impl From<MessageUpdate> for app::Message {
                 //println!("sono in key pressed {:?}", code);
                 app::Message::KeyShortcut(code)
             }
+            MessageUpdate::Fps(_) => app::Message::None,
+            MessageUpdate::JoinRequests(_) => app::Message::None,
+            MessageUpdate::ToggleViewers => app::Message::ToggleViewers,
         }
     }
 }
@@ -59,6 +71,18 @@ impl<'a> Component<'a> for CasterStreaming {
                 Command::none()
             }
             MessageUpdate::KeyPressed(_) => Command::none(),
+            MessageUpdate::Fps(fps) => {
+                self.fps = fps;
+                Command::none()
+            }
+            MessageUpdate::JoinRequests(join_requests) => {
+                self.join_requests = join_requests;
+                Command::none()
+            }
+            MessageUpdate::ToggleViewers => {
+                self.show_viewers = !self.show_viewers;
+                Command::none()
+            }
         }
     }
 
@@ -121,6 +145,14 @@ impl<'a> Component<'a> for CasterStreaming {
             }
         };
 
+        // L'elenco dei receiver resta apribile anche mentre la barra è ridotta
+        let viewers_button = MyButton::new(&format!("{}", viewrs.len()))
+            .style(Style::Secondary)
+            .icon(crate::gui::theme::icon::Icon::Viewers)
+            .build()
+            .padding(12)
+            .on_press(MessageUpdate::ToggleViewers.into());
+
         // Define the control buttons (e.g., play/pause, tools)
         let menu = if !self.toggler {
             row![
@@ -145,12 +177,8 @@ impl<'a> Component<'a> for CasterStreaming {
                     .build(30)
                     .padding(8)
                     .on_press(app::Message::Close),
-                MyButton::new(&format!("{}", viewrs))
-                    .style(Style::Secondary)
-                    .icon(crate::gui::theme::icon::Icon::Viewers)
-                    .build()
-                    .padding(12)
-                    //.on_press(app::Message::Back(app::Page::CasterStreaming)),
+                viewers_button,
+                text(format!("{} fps", self.fps)),
             ]
             .align_items(iced::Alignment::Center)
             .padding(8)
@@ -173,19 +201,68 @@ impl<'a> Component<'a> for CasterStreaming {
                     .icon(crate::gui::theme::icon::Icon::Phone)
                     .build(30)
                     .padding(8),
-                MyButton::new(&format!("{}", viewrs))
-                    .style(Style::Secondary)
-                    .icon(crate::gui::theme::icon::Icon::Viewers)
-                    .build()
-                    .padding(12),
+                viewers_button,
+                text(format!("{} fps", self.fps)),
             ]
             .align_items(iced::Alignment::Center)
             .padding(8)
             .spacing(10)
         };
 
+        // Un receiver entra solo dopo che il caster lo ha approvato
+        let join_requests = self.join_requests.iter().fold(Column::new(), |column, request| {
+            column.push(
+                row![
+                    text(format!("{} wants to watch your screen", request)),
+                    MyButton::new("Accept")
+                        .style(Style::Success)
+                        .build()
+                        .on_press(app::Message::ApproveReceiver(request.address.clone())),
+                    MyButton::new("Deny")
+                        .style(Style::Danger)
+                        .build()
+                        .on_press(app::Message::DenyReceiver(request.address.clone())),
+                ]
+                .align_items(iced::Alignment::Center)
+                .spacing(10),
+            )
+        });
+
+        let viewers = if self.show_viewers {
+            viewrs.iter().fold(Column::new(), |column, viewer| {
+                let name = match &viewer.display_name {
+                    Some(name) => format!("{} ({})", name, viewer.address),
+                    None => viewer.address.clone(),
+                };
+                column.push(
+                    row![
+                        text(format!(
+                            "{} - connected for {} - {:.0}% lost - {} queued, {} skipped",
+                            name,
+                            connected_for(viewer.joined_at),
+                            viewer.loss * 100.0,
+                            viewer.queue_depth,
+                            viewer.dropped_frames
+                        )),
+                        MyButton::new("Disconnect")
+                            .style(Style::Secondary)
+                            .build()
+                            .on_press(app::Message::KickReceiver(viewer.address.clone())),
+                        MyButton::new("Ban")
+                            .style(Style::Danger)
+                            .build()
+                            .on_press(app::Message::BanReceiver(viewer.address.clone())),
+                    ]
+                    .align_items(iced::Alignment::Center)
+                    .spacing(10),
+                )
+            })
+        } else {
+            Column::new()
+        };
+
         let streaming = container(
-            column_iced![image, menu]
+            column_iced![join_requests.spacing(4), viewers.spacing(4), image, menu]
                 .spacing(8)
                 .align_items(iced::Alignment::Center),
         );
@@ -216,3 +293,13 @@ impl<'a> Component<'a> for CasterStreaming {
         })
     }
 }
+
+// Da quanto tempo il receiver è connesso, in minuti e secondi
+fn connected_for(joined_at: Instant) -> String {
+    let seconds = joined_at.elapsed().as_secs();
+    if seconds < 60 {
+        format!("{}s", seconds)
+    } else {
+        format!("{}m {}s", seconds / 60, seconds % 60)
+    }
+}
@@ -1,29 +1,124 @@
 use iced::alignment::{Horizontal, Vertical};
-use iced::widget::{container, row};
+use iced::widget::{container, image, pick_list, row, Image};
 use iced::Length::Fill;
-use iced::Subscription;
+use iced::{Command, Subscription};
+use local_ip_address::{list_afinet_netifas, local_ip};
+use qrcode::QrCode;
+use rand::Rng;
+use std::net::{IpAddr, SocketAddr};
 use crate::gui::component::Component;
 use crate::gui::theme::button::Style;
 use crate::gui::theme::icon::Icon;
 use crate::gui::theme::text::{bold, text};
+use crate::gui::theme::textinput::textinput;
 use crate::gui::theme::button::circle_button::CircleButton;
 
 use crate::gui::app;
 use crate::gui::theme::button::MyButton;
 use crate::gui::theme::widget::{Column, Row};
+use crate::gui::resource;
+use crate::socket::session_uri::SessionUri;
+use crate::socket::socket::{default_session_name, AccessPolicy, DEFAULT_PORT, MAX_PASSPHRASE_LEN};
+use crate::socket::transport::TransportKind;
+
+const QR_MODULE_SIZE: usize = 6; // Pixel per modulo del QR code
+const QR_QUIET_ZONE: usize = 4; // Moduli di bordo chiaro richiesti dai lettori
 
 pub struct Connection {
-    pub ip_address: String,
+    pub ip_address: Option<IpAddr>, // Interfaccia su cui il caster resta in ascolto
+    pub interfaces: Vec<IpAddr>, // Indirizzi delle interfacce di rete disponibili
+    pub port: String,
+    pub message: String, // Errore mostrato se la socket non si apre
+    pub passphrase: String, // PIN o passphrase richiesti ai receiver, vuoto se la sessione è libera
+    pub require_approval: bool, // Ogni receiver va approvato dalla pagina di streaming
+    pub transport: TransportKind, // Scelto nelle impostazioni, riportato nell'URI della sessione
+    pub qr_code: Option<image::Handle>, // URI della sessione, rigenerato a ogni modifica
 }
 
 #[derive(Debug, Clone)]
 pub enum Message {
     StartSharing,
+    SelectInterface(IpAddr),
+    ChangePort(String),
+    ChangePassphrase(String),
+    GeneratePin,
+    ToggleApproval,
+    CopyUri,
 }
 
 impl From<Message> for app::Message {
-    fn from(_message: Message) -> Self {
-        app::Message::StartSharing
+    fn from(message: Message) -> Self {
+        match message {
+            Message::StartSharing => app::Message::StartSharing,
+            _ => app::Message::ConnectionInput(message),
+        }
+    }
+}
+
+impl Connection {
+    /// Aggiorna l'elenco delle interfacce, scegliendo quella predefinita se non ce n'è una valida.
+    /// Il gruppo multicast è IPv4: con questo trasporto si escludono le interfacce IPv6.
+    pub fn refresh_interfaces(&mut self, transport: TransportKind) {
+        let ipv4_only = transport == TransportKind::Multicast;
+        self.transport = transport;
+        self.interfaces = list_afinet_netifas()
+            .map(|interfaces| {
+                interfaces
+                    .into_iter()
+                    .map(|(_, ip)| ip)
+                    .filter(|ip| match ip {
+                        IpAddr::V4(_) => true,
+                        // Gli indirizzi link-local richiedono lo scope dell'interfaccia, non indicabile qui
+                        IpAddr::V6(ip) => !ipv4_only && ip.segments()[0] & 0xffc0 != 0xfe80,
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        if !self.ip_address.is_some_and(|ip| self.interfaces.contains(&ip)) {
+            self.ip_address = local_ip().ok().or(self.interfaces.first().copied());
+        }
+        self.refresh_qr_code();
+    }
+
+    /// URI con cui i receiver si connettono alla sessione, se l'indirizzo è valido.
+    pub fn session_uri(&self) -> Option<SessionUri> {
+        let address = self.bind_address().ok()?;
+        Some(SessionUri {
+            host: match address.ip() {
+                IpAddr::V6(ip) => format!("[{}]", ip),
+                ip => ip.to_string(),
+            },
+            port: address.port(),
+            session: Some(default_session_name()),
+            pin: self.access_policy().passphrase,
+            transport: self.transport,
+        })
+    }
+
+    fn refresh_qr_code(&mut self) {
+        self.qr_code = self.session_uri().and_then(|uri| qr_code(&uri.to_string()));
+    }
+
+    /// Indirizzo su cui aprire la socket del caster.
+    pub fn bind_address(&self) -> Result<SocketAddr, String> {
+        let ip = self.ip_address.ok_or("No network interface available")?;
+        let port = self.port.trim();
+        if port.is_empty() {
+            return Ok(SocketAddr::new(ip, DEFAULT_PORT));
+        }
+        match port.parse::<u16>() {
+            Ok(port) => Ok(SocketAddr::new(ip, port)),
+            Err(_) => Err(format!("Invalid port: {}", port)),
+        }
+    }
+
+    /// Regole di accesso alla sessione scelte dal caster.
+    pub fn access_policy(&self) -> AccessPolicy {
+        let passphrase = self.passphrase.trim();
+        AccessPolicy {
+            passphrase: (!passphrase.is_empty()).then(|| passphrase.to_string()),
+            require_approval: self.require_approval,
+        }
     }
 }
 
@@ -33,6 +128,36 @@ impl<'a> Component<'a> for Connection {
     fn update(&mut self, message: Self::Message) -> iced::Command<crate::gui::app::Message> {
         match message {
             Message::StartSharing => todo!(),
+            Message::SelectInterface(ip) => {
+                self.ip_address = Some(ip);
+                self.message = "".to_string();
+                self.refresh_qr_code();
+                Command::none()
+            }
+            Message::ChangePort(port) => {
+                self.port = port;
+                self.message = "".to_string();
+                self.refresh_qr_code();
+                Command::none()
+            }
+            Message::ChangePassphrase(passphrase) => {
+                self.passphrase = passphrase.chars().take(MAX_PASSPHRASE_LEN).collect();
+                self.refresh_qr_code();
+                Command::none()
+            }
+            Message::GeneratePin => {
+                self.passphrase = format!("{:06}", rand::thread_rng().gen_range(0..1_000_000));
+                self.refresh_qr_code();
+                Command::none()
+            }
+            Message::ToggleApproval => {
+                self.require_approval = !self.require_approval;
+                Command::none()
+            }
+            Message::CopyUri => match self.session_uri() {
+                Some(uri) => iced::clipboard::write(uri.to_string()),
+                None => Command::none(),
+            },
         }
     }
 
@@ -46,6 +171,27 @@ impl<'a> Component<'a> for Connection {
         .align_x(Horizontal::Left)
         .align_y(Vertical::Top);
 
+        // Link e QR code con cui i receiver si connettono senza digitare l'indirizzo
+        let session_link = match (self.session_uri(), self.qr_code.clone()) {
+            (Some(uri), Some(qr_code)) => Row::new()
+                .push(Image::new(qr_code).width(140).height(140))
+                .push(
+                    Column::new()
+                        .push(text(uri.to_string()).size(16))
+                        .push(
+                            MyButton::new("Copy link")
+                                .style(Style::Secondary)
+                                .build()
+                                .on_press(Message::CopyUri.into()),
+                        )
+                        .spacing(8)
+                        .width(420),
+                )
+                .spacing(16)
+                .align_items(iced::Alignment::Center),
+            _ => Row::new(),
+        };
+
         let main_content = container(
             Column::new().push(
                 Row::new().push(
@@ -53,8 +199,57 @@ impl<'a> Component<'a> for Connection {
                 ).align_items(iced::Alignment::Center)
             )
                 .push(
-                    text(self.ip_address.clone()).size(30)
+                    Row::new()
+                        .push(
+                            pick_list(self.interfaces.clone(), self.ip_address, |ip| {
+                                Message::SelectInterface(ip).into()
+                            })
+                            .font(resource::font::BARLOW)
+                            .text_size(30)
+                            .width(250),
+                        )
+                        .push(
+                            textinput(&DEFAULT_PORT.to_string(), self.port.as_str())
+                                .width(100)
+                                .size(30)
+                                .on_input(|port| Message::ChangePort(port).into()),
+                        )
+                        .spacing(8)
+                        .align_items(iced::Alignment::Center),
                 ).align_items(iced::Alignment::Center)
+                .push(
+                    Row::new()
+                        .push(
+                            textinput("Session PIN (optional)", self.passphrase.as_str())
+                                .width(250)
+                                .size(22)
+                                .on_input(|passphrase| Message::ChangePassphrase(passphrase).into()),
+                        )
+                        .push(
+                            MyButton::new("Generate PIN")
+                                .style(Style::Secondary)
+                                .build()
+                                .on_press(Message::GeneratePin.into()),
+                        )
+                        .spacing(8)
+                        .align_items(iced::Alignment::Center),
+                )
+                .push(
+                    text("A short PIN only keeps out casual viewers: anyone capturing the traffic can guess it. Use a long passphrase on untrusted networks.")
+                        .size(14),
+                )
+                .push(
+                    MyButton::new(if self.require_approval {
+                        "Approve each receiver: ON"
+                    } else {
+                        "Approve each receiver: OFF"
+                    })
+                    .style(if self.require_approval { Style::Primary } else { Style::Secondary })
+                    .build()
+                    .on_press(Message::ToggleApproval.into()),
+                )
+                .push(session_link)
+                .push(text(self.message.clone()))
                 .push(MyButton::new("CONNECT")
                     .style(Style::Primary)
                     .build()
@@ -80,3 +275,26 @@ impl<'a> Component<'a> for Connection {
         todo!()
     }
 }
+
+// Disegna il QR code di `data`, nero su bianco
+fn qr_code(data: &str) -> Option<image::Handle> {
+    let code = QrCode::new(data.as_bytes()).ok()?;
+    let modules = code.width();
+    let side = (modules + 2 * QR_QUIET_ZONE) * QR_MODULE_SIZE;
+    let mut pixels = vec![255u8; side * side * 4];
+    for (i, color) in code.to_colors().into_iter().enumerate() {
+        if color != qrcode::Color::Dark {
+            continue;
+        }
+        let x = (i % modules + QR_QUIET_ZONE) * QR_MODULE_SIZE;
+        let y = (i / modules + QR_QUIET_ZONE) * QR_MODULE_SIZE;
+        for row in y..y + QR_MODULE_SIZE {
+            let start = (row * side + x) * 4;
+            // Solo i canali di colore: l'alfa resta opaco
+            for pixel in pixels[start..start + QR_MODULE_SIZE * 4].chunks_mut(4) {
+                pixel[..3].fill(0);
+            }
+        }
+    }
+    Some(image::Handle::from_pixels(side as u32, side as u32, pixels))
+}
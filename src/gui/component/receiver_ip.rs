@@ -1,5 +1,5 @@
 use iced::alignment::{Horizontal, Vertical};
-use iced::widget::{container, row};
+use iced::widget::{container, pick_list, row};
 use iced::{Command, Subscription};
 use iced::Length::Fill;
 use crate::column_iced;
@@ -13,16 +13,56 @@ use crate::gui::theme::icon::Icon;
 use crate::gui::theme::text::bold;
 use crate::gui::theme::textinput::textinput;
 use crate::gui::theme::widget::Element;
+use crate::gui::resource;
+use crate::socket::discovery::DiscoveryBrowser;
+use crate::socket::session_uri::SessionUri;
+use crate::socket::socket::{MAX_DISPLAY_NAME_LEN, MAX_PASSPHRASE_LEN};
+use crate::socket::transport::TransportKind;
+use std::net::SocketAddr;
 
 pub struct ReceiverIp {
     pub indirizzo_ip: String,
-    pub message: String
+    pub message: String,
+    pub transport: TransportKind,
+    pub passphrase: String, // PIN o passphrase della sessione, se il caster lo richiede
+    pub display_name: String, // Nome mostrato al caster se deve approvare la richiesta
+    pub session: Option<String>, // Nome della sessione indicato dal link screencast://
+    pub discovery: Option<DiscoveryBrowser>, // Attiva solo mentre la pagina è aperta
+}
+
+impl ReceiverIp {
+    /// Compila i campi con i parametri di un URI screencast://.
+    pub fn apply_uri(&mut self, uri: SessionUri) {
+        self.indirizzo_ip = uri.address();
+        self.transport = uri.transport;
+        self.session = uri.session;
+        if let Some(pin) = uri.pin {
+            self.passphrase = pin.chars().take(MAX_PASSPHRASE_LEN).collect();
+        }
+        self.message = "".to_string();
+    }
+
+    /// Passphrase da inviare con la registrazione, se inserita.
+    pub fn passphrase(&self) -> Option<String> {
+        let passphrase = self.passphrase.trim();
+        (!passphrase.is_empty()).then(|| passphrase.to_string())
+    }
+
+    /// Nome con cui presentarsi al caster, se inserito.
+    pub fn display_name(&self) -> Option<String> {
+        let display_name = self.display_name.trim();
+        (!display_name.is_empty()).then(|| display_name.to_string())
+    }
 }
 
 #[derive(Debug, Clone)]
 pub enum Message {
     ChangeInput(String),
     Pressed(String),
+    SelectTransport(TransportKind),
+    ChangePassphrase(String),
+    ChangeDisplayName(String),
+    SelectSession(SocketAddr, TransportKind),
 }
 
 impl From<Message> for app::Message {
@@ -32,6 +72,18 @@ impl From<Message> for app::Message {
                 app::Message::ReceiverInputIp(Message::ChangeInput(input))
             }
             Message::Pressed(ip) => app::Message::ReceiverSharing(ip),
+            Message::SelectTransport(transport) => {
+                app::Message::ReceiverInputIp(Message::SelectTransport(transport))
+            }
+            Message::ChangePassphrase(passphrase) => {
+                app::Message::ReceiverInputIp(Message::ChangePassphrase(passphrase))
+            }
+            Message::ChangeDisplayName(display_name) => {
+                app::Message::ReceiverInputIp(Message::ChangeDisplayName(display_name))
+            }
+            Message::SelectSession(address, transport) => {
+                app::Message::ReceiverInputIp(Message::SelectSession(address, transport))
+            }
         }
     }
 }
@@ -42,11 +94,39 @@ impl<'a> Component<'a> for ReceiverIp {
     fn update(&mut self, message: Self::Message) -> iced::Command<app::Message> {
         match message {
             Message::ChangeInput(new_value) => {
-                self.indirizzo_ip = new_value;
-                self.message = "".to_string();
+                // Un URI incollato porta con sé anche trasporto e PIN
+                match new_value.parse::<SessionUri>() {
+                    Ok(uri) => self.apply_uri(uri),
+                    Err(_) => {
+                        // L'indirizzo non è più quello del link
+                        self.indirizzo_ip = new_value;
+                        self.session = None;
+                        self.message = "".to_string();
+                    }
+                }
                 Command::none()
             }
             Message::Pressed(_ip) => Command::none(),
+            Message::SelectTransport(transport) => {
+                self.transport = transport;
+                Command::none()
+            }
+            Message::ChangePassphrase(passphrase) => {
+                self.passphrase = passphrase.chars().take(MAX_PASSPHRASE_LEN).collect();
+                self.message = "".to_string();
+                Command::none()
+            }
+            Message::ChangeDisplayName(display_name) => {
+                self.display_name = display_name.chars().take(MAX_DISPLAY_NAME_LEN).collect();
+                Command::none()
+            }
+            Message::SelectSession(address, transport) => {
+                self.indirizzo_ip = address.to_string();
+                self.transport = transport;
+                self.session = None;
+                self.message = "".to_string();
+                Command::none()
+            }
         }
     }
 
@@ -61,17 +141,66 @@ impl<'a> Component<'a> for ReceiverIp {
             .align_y(Vertical::Top);
 
         let message = row![crate::gui::theme::text::text(self.message.clone())];
+        let session = row![crate::gui::theme::text::text(match &self.session {
+            Some(session) => format!("Session: {}", session),
+            None => "".to_string(),
+        })];
+
+        // Sessioni annunciate sulla rete locale: un clic compila indirizzo e trasporto
+        let sessions = self
+            .discovery
+            .as_ref()
+            .map(DiscoveryBrowser::sessions)
+            .unwrap_or_default();
+        let discovered = if sessions.is_empty() {
+            column_iced![crate::gui::theme::text::text("Looking for casters on the local network...")]
+        } else {
+            sessions.into_iter().fold(
+                column_iced![bold("Casters on the local network").size(22)],
+                |column, session| {
+                    column.push(
+                        MyButton::new(&session.to_string())
+                            .style(Style::Secondary)
+                            .build()
+                            .on_press(Message::SelectSession(session.address, session.transport).into()),
+                    )
+                },
+            )
+        }
+        .align_items(iced::Alignment::Center)
+        .spacing(8);
 
         let main_content = container(
             column_iced![
-                row![bold("Insert IP address").size(60)],
-                row![textinput("192.168.1.1", self.indirizzo_ip.as_str())
+                row![bold("Insert caster address").size(60)],
+                session,
+                row![textinput("IP, hostname or screencast:// link", self.indirizzo_ip.as_str())
                     .width(300)
                     .size(27)
                     .on_input(|written_ip| {
                         receiver_ip::Message::ChangeInput(written_ip).into()
+                    }),
+                    pick_list(TransportKind::ALL, Some(self.transport), |transport| {
+                        receiver_ip::Message::SelectTransport(transport).into()
+                    })
+                    .font(resource::font::BARLOW)
+                    .width(80)]
+                .spacing(8)
+                .align_items(iced::Alignment::Center),
+                row![textinput("Session PIN (if required)", self.passphrase.as_str())
+                    .width(388)
+                    .size(22)
+                    .on_input(|passphrase| {
+                        receiver_ip::Message::ChangePassphrase(passphrase).into()
+                    })],
+                row![textinput("Your name (optional)", self.display_name.as_str())
+                    .width(388)
+                    .size(22)
+                    .on_input(|display_name| {
+                        receiver_ip::Message::ChangeDisplayName(display_name).into()
                     })],
                     message,
+                discovered,
                 row![MyButton::new("Connect")
                     .style(Style::Primary)
                     .build()
@@ -1,5 +1,7 @@
-use iced::widget::{container, image, row, Image};
+use iced::alignment::{Horizontal, Vertical};
+use iced::widget::{container, image, pick_list, row, Image};
 use iced::{Command, Subscription};
+use std::fmt;
 use std::sync::Arc;
 use tokio::sync::{Mutex, mpsc::Receiver};
 
@@ -7,26 +9,79 @@ use crate::gui::app;
 use crate::gui::component::Component;
 use crate::gui::theme::button::circle_button::CircleButton;
 use crate::gui::theme::button::Style;
+use crate::gui::theme::container::Style as ContainerStyle;
 use crate::gui::theme::icon::Icon;
+use crate::gui::theme::text::{bold, text};
+use crate::gui::theme::textinput::textinput;
 use crate::gui::theme::widget::{Column, Element};
+use crate::gui::resource;
 use xcap::image::RgbaImage;
+use crate::socket::socket::{StreamInfo, StreamStatus};
+
+/// Cosa mostrare al posto dello stream quando il caster oscura lo schermo.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BlankPlaceholder {
+    #[default]
+    Black,
+    Logo,
+    Message,
+}
+
+impl BlankPlaceholder {
+    pub const ALL: [BlankPlaceholder; 3] = [
+        BlankPlaceholder::Black,
+        BlankPlaceholder::Logo,
+        BlankPlaceholder::Message,
+    ];
+}
+
+impl fmt::Display for BlankPlaceholder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BlankPlaceholder::Black => write!(f, "Black screen"),
+            BlankPlaceholder::Logo => write!(f, "Logo"),
+            BlankPlaceholder::Message => write!(f, "Custom message"),
+        }
+    }
+}
 
 pub struct ReceiverStreaming {
     pub recording: bool,
     pub receiver: Arc<Mutex<Receiver<RgbaImage>>>,
     pub frame_to_update: Arc<Mutex<Option<RgbaImage>>>,
     pub is_loading: bool,
+    pub dropped_frames: u64,
+    pub recovered_chunks: u64, // Chunk persi ma ricostruiti con la FEC
+    pub rejected_packets: u64, // Datagrammi estranei o corrotti scartati
+    pub caster_fps: u32,
+    pub stream_info: Option<StreamInfo>, // Parametri ricevuti alla registrazione
+    pub status: StreamStatus, // Stato della trasmissione annunciato dal caster
+    pub placeholder: BlankPlaceholder, // Mostrato localmente a schermo oscurato
+    pub placeholder_message: String,
 }
 
 #[derive(Debug, Clone)]
 pub enum UpdateMessage {
     StartRecording(bool),
     NewFrame(RgbaImage),
+    Stats {
+        dropped_frames: u64,
+        recovered_chunks: u64,
+        rejected_packets: u64,
+        caster_fps: u32,
+    },
+    Connected(StreamInfo),
+    Status(StreamStatus),
+    SelectPlaceholder(BlankPlaceholder),
+    ChangePlaceholderMessage(String),
 }
 
 impl From<UpdateMessage> for app::Message {
     fn from(message: UpdateMessage) -> Self {
-        app::Message::StartRecording(message)
+        match message {
+            UpdateMessage::StartRecording(_) => app::Message::StartRecording(message),
+            _ => app::Message::UpdateReceiverStreaming(message),
+        }
     }
 }
 
@@ -46,6 +101,35 @@ impl<'a> Component<'a> for ReceiverStreaming {
                 self.is_loading = false;
                 Command::none()
             },
+            UpdateMessage::Stats {
+                dropped_frames,
+                recovered_chunks,
+                rejected_packets,
+                caster_fps,
+            } => {
+                self.dropped_frames = dropped_frames;
+                self.recovered_chunks = recovered_chunks;
+                self.rejected_packets = rejected_packets;
+                self.caster_fps = caster_fps;
+                Command::none()
+            }
+            UpdateMessage::Connected(stream_info) => {
+                self.stream_info = Some(stream_info);
+                self.status = StreamStatus::Live;
+                Command::none()
+            }
+            UpdateMessage::Status(status) => {
+                self.status = status;
+                Command::none()
+            }
+            UpdateMessage::SelectPlaceholder(placeholder) => {
+                self.placeholder = placeholder;
+                Command::none()
+            }
+            UpdateMessage::ChangePlaceholderMessage(message) => {
+                self.placeholder_message = message;
+                Command::none()
+            }
         }
     }
 
@@ -69,6 +153,54 @@ impl<'a> Component<'a> for ReceiverStreaming {
 
         };
 
+        // Con lo schermo oscurato l'ultimo frame non va più mostrato
+        let stream: Element<'_, app::Message> = match (self.status, self.placeholder) {
+            (StreamStatus::Blanked, BlankPlaceholder::Logo) => {
+                Image::new("./resources/icons/512x512.png")
+                    .width(iced::Length::Fill)
+                    .height(iced::Length::Fill)
+                    .into()
+            }
+            (StreamStatus::Blanked, placeholder) => {
+                let message = match placeholder {
+                    BlankPlaceholder::Message => self.placeholder_message.as_str(),
+                    _ => "",
+                };
+                container(bold(message).size(30))
+                    .style(ContainerStyle::Container)
+                    .width(iced::Length::Fill)
+                    .height(iced::Length::Fill)
+                    .align_x(Horizontal::Center)
+                    .align_y(Vertical::Center)
+                    .into()
+            }
+            _ => image.into(),
+        };
+
+        let mut placeholder_settings = row![
+            text("When the screen is hidden:"),
+            pick_list(BlankPlaceholder::ALL, Some(self.placeholder), |placeholder| {
+                UpdateMessage::SelectPlaceholder(placeholder).into()
+            })
+            .font(resource::font::BARLOW)
+            .width(160),
+        ]
+        .align_items(iced::Alignment::Center)
+        .spacing(8);
+        if self.placeholder == BlankPlaceholder::Message {
+            placeholder_settings = placeholder_settings.push(
+                textinput("Be right back", self.placeholder_message.as_str())
+                    .width(250)
+                    .on_input(|message| UpdateMessage::ChangePlaceholderMessage(message).into()),
+            );
+        }
+        let status = text(match self.status {
+            StreamStatus::Live => "",
+            StreamStatus::Paused => "Transmission paused by the caster",
+            StreamStatus::Blanked => "Screen hidden by the caster",
+            StreamStatus::Ended => "The caster ended the session",
+        });
+
         //let stream = Element::from(image).explain(Color::WHITE);
 
         let buttons = if self.recording {
@@ -83,7 +215,12 @@ impl<'a> Component<'a> for ReceiverStreaming {
                     .icon(Icon::Cancel)
                     .build(21)
                     .on_press(app::Message::Back(app::Page::ReceiverStreaming)),
+                text(format!(
+                    "{} fps - Dropped frames: {} - Recovered chunks: {} - Rejected packets: {}",
+                    self.caster_fps, self.dropped_frames, self.recovered_chunks, self.rejected_packets
+                )),
             ]
+            .align_items(iced::Alignment::Center)
             .spacing(5)
             .padding(8)
         } else {
@@ -98,13 +235,29 @@ impl<'a> Component<'a> for ReceiverStreaming {
                     .icon(Icon::Cancel)
                     .build(21)
                     .on_press(app::Message::Close),
+                text(format!(
+                    "{} fps - Dropped frames: {} - Recovered chunks: {} - Rejected packets: {}",
+                    self.caster_fps, self.dropped_frames, self.recovered_chunks, self.rejected_packets
+                )),
             ]
+            .align_items(iced::Alignment::Center)
             .spacing(5)
             .padding(8)
         };
         //let screen = column_iced![row![image].spacing(20)];
+        let session = text(
+            self.stream_info
+                .as_ref()
+                .map(|info| info.to_string())
+                .unwrap_or_default(),
+        );
         container(
-            Column::new().push(image).push(buttons)
+            Column::new()
+                .push(session)
+                .push(status)
+                .push(stream)
+                .push(buttons)
+                .push(placeholder_settings)
                 .spacing(8)
                 .align_items(iced::Alignment::Center),
         )
@@ -1,4 +1,5 @@
 use crate::gui::app::App;
+use crate::socket::session_uri::SessionUri;
 use iced::{Font, Pixels, Settings, Size, window};
 use iced::multi_window::Application;
 use iced::window::{Level, Position};
@@ -10,9 +11,18 @@ mod controller;
 mod socket;
 mod utils;
 mod model;
+mod codec;
 
 
 pub fn main() -> iced::Result {
+    // Un URI screencast:// sulla riga di comando apre direttamente la sessione
+    let uri = std::env::args().nth(1).and_then(|arg| match arg.parse::<SessionUri>() {
+        Ok(uri) => Some(uri),
+        Err(e) => {
+            eprintln!("URI non valido {}: {}", arg, e);
+            None
+        }
+    });
     App::run(Settings{
         id: None,
         window: window::Settings{
@@ -29,7 +39,7 @@ pub fn main() -> iced::Result {
             exit_on_close_request: true,
             platform_specific: PlatformSpecific::default(),
         },
-        flags: Default::default(),
+        flags: uri,
         fonts: Vec::new(),
         default_font: Font::default(),
         default_text_size: Pixels(16.0),
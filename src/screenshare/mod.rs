@@ -1 +1,2 @@
-pub mod screenshare;
\ No newline at end of file
+pub mod screenshare;
+pub mod scaling;
\ No newline at end of file
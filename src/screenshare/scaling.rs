@@ -0,0 +1,128 @@
+use std::fmt;
+
+use xcap::image::imageops::{self, FilterType};
+use xcap::image::RgbaImage;
+
+/// Risoluzione dei frame inviati ai receiver, indipendente da quella del monitor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputResolution {
+    /// Nessun ridimensionamento
+    #[default]
+    Native,
+    /// Percentuale della risoluzione catturata
+    Percent(u32),
+    /// Dimensioni esatte, senza mantenere le proporzioni
+    Fixed { width: u32, height: u32 },
+    /// Riduce il frame per farlo stare nel riquadro, mantenendo le proporzioni
+    FitWithin { width: u32, height: u32 },
+}
+
+impl OutputResolution {
+    /// Risoluzioni selezionabili nelle impostazioni del caster.
+    pub const PRESETS: [OutputResolution; 8] = [
+        OutputResolution::Native,
+        OutputResolution::Percent(75),
+        OutputResolution::Percent(50),
+        OutputResolution::Percent(25),
+        OutputResolution::FitWithin { width: 1920, height: 1080 },
+        OutputResolution::FitWithin { width: 1280, height: 720 },
+        OutputResolution::FitWithin { width: 854, height: 480 },
+        OutputResolution::Fixed { width: 1280, height: 720 },
+    ];
+
+    /// Dimensioni di uscita per un frame catturato di `width`x`height`.
+    pub fn target_size(&self, width: u32, height: u32) -> (u32, u32) {
+        let (target_width, target_height) = match *self {
+            OutputResolution::Native => (width, height),
+            OutputResolution::Percent(percent) => (
+                (width as u64 * percent as u64 / 100) as u32,
+                (height as u64 * percent as u64 / 100) as u32,
+            ),
+            OutputResolution::Fixed { width, height } => (width, height),
+            OutputResolution::FitWithin {
+                width: max_width,
+                height: max_height,
+            } => {
+                // Solo riduzione: un frame già più piccolo resta invariato
+                let ratio = (max_width as f64 / width.max(1) as f64)
+                    .min(max_height as f64 / height.max(1) as f64)
+                    .min(1.0);
+                (
+                    (width as f64 * ratio).round() as u32,
+                    (height as f64 * ratio).round() as u32,
+                )
+            }
+        };
+        (target_width.max(1), target_height.max(1))
+    }
+}
+
+impl fmt::Display for OutputResolution {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OutputResolution::Native => write!(f, "Native"),
+            OutputResolution::Percent(percent) => write!(f, "{}%", percent),
+            OutputResolution::Fixed { width, height } => write!(f, "{}x{}", width, height),
+            OutputResolution::FitWithin { width, height } => {
+                write!(f, "Fit {}x{}", width, height)
+            }
+        }
+    }
+}
+
+/// Filtro di ricampionamento, dal più veloce al più accurato.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ScalingFilter {
+    Nearest,
+    #[default]
+    Bilinear,
+    CatmullRom,
+    Lanczos3,
+}
+
+impl ScalingFilter {
+    pub const ALL: [ScalingFilter; 4] = [
+        ScalingFilter::Nearest,
+        ScalingFilter::Bilinear,
+        ScalingFilter::CatmullRom,
+        ScalingFilter::Lanczos3,
+    ];
+
+    pub fn filter_type(&self) -> FilterType {
+        match self {
+            ScalingFilter::Nearest => FilterType::Nearest,
+            ScalingFilter::Bilinear => FilterType::Triangle,
+            ScalingFilter::CatmullRom => FilterType::CatmullRom,
+            ScalingFilter::Lanczos3 => FilterType::Lanczos3,
+        }
+    }
+}
+
+impl fmt::Display for ScalingFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScalingFilter::Nearest => write!(f, "Nearest"),
+            ScalingFilter::Bilinear => write!(f, "Bilinear"),
+            ScalingFilter::CatmullRom => write!(f, "Catmull-Rom"),
+            ScalingFilter::Lanczos3 => write!(f, "Lanczos"),
+        }
+    }
+}
+
+/// Ridimensionamento applicato ai frame in uscita, dopo la cattura e il cursore.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct OutputScaling {
+    pub resolution: OutputResolution,
+    pub filter: ScalingFilter,
+}
+
+impl OutputScaling {
+    /// Restituisce il frame da inviare: quello originale se non va ridimensionato.
+    pub fn apply(&self, frame: RgbaImage) -> RgbaImage {
+        let (width, height) = self.resolution.target_size(frame.width(), frame.height());
+        if (width, height) == frame.dimensions() {
+            return frame;
+        }
+        imageops::resize(&frame, width, height, self.filter.filter_type())
+    }
+}
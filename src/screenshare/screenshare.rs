@@ -1,10 +1,16 @@
+use crate::codec::video::CodecSettings;
+use crate::codec::{FrameDecoder, FrameEncoder};
+use crate::screenshare::scaling::{OutputScaling, ScalingFilter};
+use crate::socket::feedback::QualityLevel;
 use crate::socket::socket::{CasterSocket, ReceiverSocket};
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tokio::sync::mpsc::Sender;
 use tokio::sync::Mutex;
-use tokio::time::timeout;
+use tokio::time::{interval, timeout, Interval, MissedTickBehavior};
+use xcap::image::imageops;
 use xcap::image::RgbaImage;
 use xcap::Monitor;
 
@@ -32,14 +38,146 @@ use x11::xfixes::*;
 use x11::xlib;
 #[cfg(target_os = "linux")]
 use x11::xlib::*;
+
+/// Frame rate di cattura usato se il caster non ne sceglie uno.
+pub const DEFAULT_TARGET_FPS: u32 = 30;
+/// Frame rate selezionabili nelle impostazioni del caster.
+pub const TARGET_FPS_OPTIONS: [u32; 6] = [5, 10, 15, 24, 30, 60];
+
+// Scandisce la cattura: se il ciclo è in ritardo i tick persi vengono saltati
+// invece di catturare a raffica per recuperare.
+fn capture_ticker(target_fps: u32) -> Interval {
+    let mut ticker = interval(Duration::from_secs_f64(1.0 / target_fps.max(1) as f64));
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Skip);
+    ticker
+}
+
+/// Conta i frame inviati e pubblica una volta al secondo il frame rate reale.
+struct FpsMeter {
+    frames: u32,
+    window_start: Instant,
+    measured_fps: Arc<AtomicU32>,
+}
+
+impl FpsMeter {
+    fn new(measured_fps: Arc<AtomicU32>) -> Self {
+        FpsMeter {
+            frames: 0,
+            window_start: Instant::now(),
+            measured_fps,
+        }
+    }
+
+    fn frame_sent(&mut self, socket: &CasterSocket) {
+        self.frames += 1;
+        let elapsed = self.window_start.elapsed();
+        if elapsed >= Duration::from_secs(1) {
+            let fps = (self.frames as f64 / elapsed.as_secs_f64()).round() as u32;
+            self.measured_fps.store(fps, Ordering::Relaxed);
+            socket.set_fps(fps);
+            self.frames = 0;
+            self.window_start = Instant::now();
+        }
+    }
+}
+
+impl Drop for FpsMeter {
+    fn drop(&mut self) {
+        self.measured_fps.store(0, Ordering::Relaxed);
+    }
+}
+
+/// Un encoder per ogni livello di qualità richiesto dai receiver: ogni livello
+/// ha la propria risoluzione, bitrate e frequenza di invio.
+struct QualityEncoders {
+    codec_settings: CodecSettings,
+    fps: u32,
+    filter: ScalingFilter, // Lo stesso scelto per la risoluzione in uscita
+    encoders: HashMap<QualityLevel, FrameEncoder>,
+    frame_index: u64,
+}
+
+impl QualityEncoders {
+    fn new(codec_settings: CodecSettings, fps: u32, filter: ScalingFilter) -> Self {
+        QualityEncoders {
+            codec_settings,
+            fps,
+            filter,
+            encoders: HashMap::new(),
+            frame_index: 0,
+        }
+    }
+
+    async fn send(&mut self, sock: &CasterSocket, frame: RgbaImage) {
+        sock.update_stream_info(frame.width(), frame.height(), self.codec_settings.codec, self.fps);
+        let levels = sock.quality_levels().await;
+        // Un livello che torna in uso riparte da un encoder nuovo, quindi da un keyframe
+        self.encoders.retain(|level, _| levels.contains(level));
+        if sock.take_keyframe_request() {
+            self.encoders.values_mut().for_each(FrameEncoder::force_keyframe);
+        }
+
+        let frame_index = self.frame_index;
+        self.frame_index += 1;
+        let frame = Arc::new(frame);
+        for level in levels {
+            if frame_index % level.frame_interval() != 0 {
+                continue;
+            }
+            let mut encoder = self.encoders.remove(&level).unwrap_or_else(|| {
+                let settings = CodecSettings {
+                    bitrate_kbps: self.codec_settings.bitrate_kbps * level.bitrate_percent() / 100,
+                    ..self.codec_settings
+                };
+                let fps = self.fps / level.frame_interval() as u32;
+                FrameEncoder::new_or_fallback(&settings, fps)
+            });
+
+            // Ridimensionamento e codifica in un task bloccante: l'encoder torna indietro con il risultato
+            let frame = frame.clone();
+            let filter = self.filter;
+            let encoded = tokio::task::spawn_blocking(move || {
+                let encoded = if level.scale_percent() == 100 {
+                    encoder.encode(&frame)
+                } else {
+                    let width = (frame.width() * level.scale_percent() / 100).max(1);
+                    let height = (frame.height() * level.scale_percent() / 100).max(1);
+                    encoder.encode(&imageops::resize(&*frame, width, height, filter.filter_type()))
+                };
+                (encoder, encoded)
+            })
+            .await;
+            match encoded {
+                Ok((encoder, encoded)) => {
+                    self.encoders.insert(level, encoder);
+                    match encoded {
+                        Ok(encoded) => sock.send_to_receivers(level, encoded).await,
+                        Err(e) => eprintln!("Error encoding frame for {:?} quality: {}", level, e),
+                    }
+                }
+                // L'encoder è perso: al prossimo frame ne riparte uno nuovo da un keyframe
+                Err(e) => eprintln!("Encoding task failed for {:?} quality: {:?}", level, e),
+            }
+        }
+    }
+}
+
 pub async fn start_screen_sharing(
     monitor: Arc<std::sync::Mutex<Monitor>>,
     stop_flag: Arc<AtomicBool>,
     sender: Arc<tokio::sync::mpsc::Sender<RgbaImage>>,
     socket: Arc<tokio::sync::Mutex<Option<CasterSocket>>>,
     blanking_flag: Arc<AtomicBool>,
+    codec_settings: CodecSettings,
+    target_fps: u32,
+    measured_fps: Arc<AtomicU32>,
+    output_scaling: OutputScaling,
 ) {
+    let mut encoders = QualityEncoders::new(codec_settings, target_fps, output_scaling.filter);
+    let mut ticker = capture_ticker(target_fps);
+    let mut fps_meter = FpsMeter::new(measured_fps);
     while !stop_flag.load(Ordering::Relaxed) {
+        ticker.tick().await;
         // Cattura lo schermo in un task bloccante
         let frame_result = tokio::task::spawn_blocking({
             let monitor = monitor.clone();
@@ -137,22 +275,27 @@ pub async fn start_screen_sharing(
                 eprintln!("Error sending frame data: {:?}", send_err);
             }
 
-            // Invia il frame ai socket dei peer
-            let sock_lock = socket.lock().await;
-            if let Some(sock) = sock_lock.as_ref() {
-                if blanking_flag.load(Ordering::Relaxed) {
-                    //frame nero
-                    //println!("Mando frame nero");
-                    let black_frame_data = vec![0u8; (width * height * 4) as usize]; // RGBA: 4 byte per pixel
-                    if let Some(black_frame) = RgbaImage::from_raw(width, height, black_frame_data)
-                    {
-                        sock.send_to_receivers(black_frame).await;
-                    } else {
-                        eprintln!("Error creating black frame");
+            // Schermo oscurato: i receiver lo sanno già dal messaggio di controllo, nessun frame da inviare
+            if blanking_flag.load(Ordering::Relaxed) {
+                continue;
+            }
+
+            // Ridimensiona il frame per i receiver, l'anteprima locale resta nativa
+            let new_frame =
+                match tokio::task::spawn_blocking(move || output_scaling.apply(new_frame)).await {
+                    Ok(frame) => frame,
+                    Err(e) => {
+                        eprintln!("Error scaling frame: {:?}", e);
+                        continue;
                     }
-                } else {
-                    sock.send_to_receivers(new_frame).await;
-                }
+                };
+
+            // Invia il frame ai socket dei peer: la socket si clona fuori dal lock, così
+            // codifica e invio non bloccano chi deve modificarla
+            let sock = socket.lock().await.clone();
+            if let Some(sock) = sock {
+                encoders.send(&sock, new_frame).await;
+                fps_meter.frame_sent(&sock);
             } else {
                 eprintln!("No CasterSocket available");
             }
@@ -168,28 +311,51 @@ pub async fn start_screen_receiving(
     sender: Arc<Sender<RgbaImage>>,
     socket: Arc<Mutex<ReceiverSocket>>,
 ) {
+    let mut decoder = FrameDecoder::default();
     while !stop_flag.load(Ordering::Relaxed) {
         // non possiamo metterla four dal while perchè si bugga nela chiusura
         let sock_lock = socket.lock().await;
 
+        // Report periodico al caster per adattare la qualità dello stream
+        if let Err(e) = sock_lock.send_feedback().await {
+            eprintln!("Error sending feedback report: {:?}", e);
+        }
+        if let Err(e) = sock_lock.send_heartbeat().await {
+            eprintln!("Error sending heartbeat: {:?}", e);
+        }
+
         // Timeout di 1 secondo per la ricezione
         match timeout(Duration::from_secs(1), sock_lock.receive_from()).await {
             Ok(Ok(serialized_image)) => {
-                if let Some(image) = RgbaImage::from_raw(
-                    serialized_image.width(),
-                    serialized_image.height(),
-                    serialized_image.data().to_vec(),
-                ) {
-                    /*println!(
-                        "Received a frame of size {}x{}",
-                        image.width(),
-                        image.height()
-                    );*/
-                    if let Err(send_err) = sender.send(image).await {
-                        eprintln!("Error sending frame data: {:?}", send_err);
+                let encoded = match serialized_image.decode() {
+                    Ok(encoded) => encoded,
+                    Err(e) => {
+                        eprintln!("Error decoding frame: {:?}", e);
+                        if let Err(e) = sock_lock.request_keyframe().await {
+                            eprintln!("Error requesting a keyframe: {:?}", e);
+                        }
+                        continue;
+                    }
+                };
+                match decoder.decode(encoded) {
+                    Ok(None) => {}
+                    Ok(Some(image)) => {
+                        /*println!(
+                            "Received a frame of size {}x{}",
+                            image.width(),
+                            image.height()
+                        );*/
+                        if let Err(send_err) = sender.send(image).await {
+                            eprintln!("Error sending frame data: {:?}", send_err);
+                        }
+                    }
+                    // Il decoder ha perso il riferimento (delta o H.264): serve un keyframe dal caster
+                    Err(e) => {
+                        eprintln!("Error creating RgbaImage from received data: {}", e);
+                        if let Err(e) = sock_lock.request_keyframe().await {
+                            eprintln!("Error requesting a keyframe: {:?}", e);
+                        }
                     }
-                } else {
-                    eprintln!("Error creating RgbaImage from received data");
                 }
             }
             Ok(Err(e)) => {
@@ -212,8 +378,17 @@ pub async fn start_partial_sharing(
     sender: Arc<Sender<RgbaImage>>,
     dimensions: [(f64, f64); 2],
     socket: Arc<tokio::sync::Mutex<Option<CasterSocket>>>,
+    blanking_flag: Arc<AtomicBool>,
+    codec_settings: CodecSettings,
+    target_fps: u32,
+    measured_fps: Arc<AtomicU32>,
+    output_scaling: OutputScaling,
 ) {
+    let mut encoders = QualityEncoders::new(codec_settings, target_fps, output_scaling.filter);
+    let mut ticker = capture_ticker(target_fps);
+    let mut fps_meter = FpsMeter::new(measured_fps);
     while !stop_flag.load(Ordering::Relaxed) {
+        ticker.tick().await;
         let frame_result = {
             let mon_lock = monitor.lock().unwrap();
             mon_lock.capture_image(Some([dimensions[0], dimensions[1]]))
@@ -313,9 +488,29 @@ pub async fn start_partial_sharing(
                         eprintln!("Error sending frame data: {:?}", send_err);
                     }
 
-                    let sock_lock = socket.lock().await;
-                    if let Some(sock) = sock_lock.as_ref() {
-                        sock.send_to_receivers(new_frame).await;
+                    // Schermo oscurato: ai receiver non arriva nessun frame
+                    if blanking_flag.load(Ordering::Relaxed) {
+                        continue;
+                    }
+
+                    // Ridimensiona il frame per i receiver, l'anteprima locale resta nativa
+                    let new_frame = match tokio::task::spawn_blocking(move || {
+                        output_scaling.apply(new_frame)
+                    })
+                    .await
+                    {
+                        Ok(frame) => frame,
+                        Err(e) => {
+                            eprintln!("Errore durante il ridimensionamento del frame: {:?}", e);
+                            continue;
+                        }
+                    };
+
+                    // La socket si clona fuori dal lock, come sugli altri sistemi
+                    let sock = socket.lock().await.clone();
+                    if let Some(sock) = sock {
+                        encoders.send(&sock, new_frame).await;
+                        fps_meter.frame_sent(&sock);
                        // println!("CASTER SOCKET: frame sent!");
                     } else {
                         eprintln!("No CasterSocket available");
@@ -0,0 +1,91 @@
+use std::io::{Read, Write};
+
+use flate2::read::{DeflateDecoder, ZlibDecoder};
+use flate2::write::{DeflateEncoder, ZlibEncoder};
+use serde::{Deserialize, Serialize};
+
+/// Algoritmo di compressione applicato ai frame prima dell'invio.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Compression {
+    None,
+    #[default]
+    Zlib,
+    Deflate,
+}
+
+impl Compression {
+    /// Algoritmi supportati, in ordine di preferenza.
+    pub const SUPPORTED: [Compression; 3] =
+        [Compression::Zlib, Compression::Deflate, Compression::None];
+
+    /// Sceglie l'algoritmo da usare con un receiver: quello preferito dal caster
+    /// se il receiver lo supporta, altrimenti il primo indicato dal receiver.
+    pub fn negotiate(preferred: Compression, offered: &[Compression]) -> Compression {
+        if offered.contains(&preferred) {
+            preferred
+        } else {
+            offered
+                .iter()
+                .copied()
+                .find(|c| Self::SUPPORTED.contains(c))
+                .unwrap_or(Compression::None)
+        }
+    }
+
+    /// Comprime `data` con il livello indicato (1 = veloce, 9 = massima compressione).
+    pub fn compress(&self, data: &[u8], level: u32) -> std::io::Result<Vec<u8>> {
+        let level = flate2::Compression::new(level.clamp(1, 9));
+        match self {
+            Compression::None => Ok(data.to_vec()),
+            Compression::Zlib => {
+                let mut encoder = ZlibEncoder::new(Vec::with_capacity(data.len() / 4), level);
+                encoder.write_all(data)?;
+                encoder.finish()
+            }
+            Compression::Deflate => {
+                let mut encoder = DeflateEncoder::new(Vec::with_capacity(data.len() / 4), level);
+                encoder.write_all(data)?;
+                encoder.finish()
+            }
+        }
+    }
+
+    pub fn decompress(&self, data: &[u8]) -> std::io::Result<Vec<u8>> {
+        let mut decompressed = Vec::with_capacity(data.len() * 4);
+        match self {
+            Compression::None => return Ok(data.to_vec()),
+            Compression::Zlib => ZlibDecoder::new(data).read_to_end(&mut decompressed)?,
+            Compression::Deflate => DeflateDecoder::new(data).read_to_end(&mut decompressed)?,
+        };
+        Ok(decompressed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn roundtrip_for_every_algorithm() {
+        let data: Vec<u8> = (0..64 * 1024).map(|i| (i / 64) as u8).collect();
+        for compression in Compression::SUPPORTED {
+            let compressed = compression.compress(&data, 6).unwrap();
+            if compression != Compression::None {
+                assert!(compressed.len() < data.len() / 4, "{:?}", compression);
+            }
+            assert_eq!(compression.decompress(&compressed).unwrap(), data);
+        }
+    }
+
+    #[test]
+    fn corrupted_data_is_an_error() {
+        assert!(Compression::Zlib.decompress(b"non compresso").is_err());
+    }
+
+    #[test]
+    fn negotiation_prefers_the_caster_choice() {
+        assert_eq!(Compression::negotiate(Compression::Deflate, &Compression::SUPPORTED), Compression::Deflate);
+        assert_eq!(Compression::negotiate(Compression::Deflate, &[Compression::Zlib]), Compression::Zlib);
+        assert_eq!(Compression::negotiate(Compression::Zlib, &[]), Compression::None);
+    }
+}
@@ -0,0 +1,366 @@
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use hkdf::Hkdf;
+use rand::rngs::OsRng;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use thiserror::Error;
+use x25519_dalek::{EphemeralSecret, PublicKey, ReusableSecret};
+
+const KEY_SIZE: usize = 32;
+const COUNTER_SIZE: usize = 8;
+const REPLAY_WINDOW: u64 = 64; // Frame fuori ordine ancora accettati dietro il più recente
+const KEY_WRAP_INFO: &[u8] = b"screencast session key";
+const FRAME_KEY_INFO: &[u8] = b"screencast frame key";
+const CONTROL_KEY_INFO: &[u8] = b"screencast control key";
+
+#[derive(Error, Debug)]
+pub enum CryptoError {
+    #[error("Encrypted frame is truncated")]
+    Truncated,
+    #[error("Frame authentication failed")]
+    Tampered,
+    #[error("Frame {0} was already received")]
+    Replayed(u64),
+    #[error("Key exchange failed")]
+    KeyExchange,
+}
+
+/// Flussi cifrati con chiavi distinte, derivate dalla chiave di sessione: un pacchetto
+/// di un flusso non si decifra sull'altro, anche a parità di contatore.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Channel {
+    Frames,
+    Control, // Messaggi di stato e disconnessioni del caster
+}
+
+impl Channel {
+    fn info(self) -> &'static [u8] {
+        match self {
+            Channel::Frames => FRAME_KEY_INFO,
+            Channel::Control => CONTROL_KEY_INFO,
+        }
+    }
+}
+
+/// Chiave di sessione del caster, consegnata a ogni receiver cifrata con la
+/// chiave concordata durante la registrazione.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct KeyGrant {
+    caster_public_key: [u8; 32],
+    wrapped_key: Vec<u8>,
+}
+
+/// Metà del receiver dello scambio X25519, valida per una sola richiesta di registrazione.
+pub struct KeyExchange {
+    secret: ReusableSecret, // Prova ogni risposta ricevuta finché una non si decifra
+    public_key: PublicKey,
+}
+
+impl KeyExchange {
+    pub fn new() -> Self {
+        let secret = ReusableSecret::random_from_rng(OsRng);
+        let public_key = PublicKey::from(&secret);
+        KeyExchange { secret, public_key }
+    }
+
+    pub fn public_key(&self) -> [u8; 32] {
+        self.public_key.to_bytes()
+    }
+
+    /// Ricava la chiave di sessione dalla risposta del caster.
+    pub fn accept(&self, grant: &KeyGrant, passphrase: Option<&str>) -> Result<[u8; KEY_SIZE], CryptoError> {
+        let caster_public_key = PublicKey::from(grant.caster_public_key);
+        let shared = self.secret.diffie_hellman(&caster_public_key);
+        if !shared.was_contributory() {
+            return Err(CryptoError::KeyExchange);
+        }
+        let wrapping = wrapping_cipher(
+            shared.as_bytes(),
+            passphrase,
+            &self.public_key.to_bytes(),
+            &grant.caster_public_key,
+        );
+        let key = wrapping
+            .decrypt(&Nonce::default(), grant.wrapped_key.as_slice())
+            .map_err(|_| CryptoError::KeyExchange)?;
+        key.try_into().map_err(|_| CryptoError::KeyExchange)
+    }
+}
+
+impl Default for KeyExchange {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Cifra frame e messaggi di controllo del caster con le chiavi derivate dalla chiave di sessione.
+///
+/// La chiave resta la stessa per tutta la sessione: un receiver disconnesso o escluso
+/// la conosce ancora, quindi l'esclusione ferma l'invio dei frame ma non revoca la chiave.
+pub struct FrameSealer {
+    key: [u8; KEY_SIZE],
+    frames: SealingCipher,
+    control: SealingCipher,
+}
+
+struct SealingCipher {
+    cipher: ChaCha20Poly1305,
+    counter: AtomicU64, // Usato come nonce: non si ripete mai con la stessa chiave
+}
+
+impl FrameSealer {
+    /// Genera una nuova chiave di sessione casuale.
+    pub fn new() -> Self {
+        let mut key = [0; KEY_SIZE];
+        OsRng.fill_bytes(&mut key);
+        let sealing = |channel| SealingCipher {
+            cipher: channel_cipher(&key, channel),
+            counter: AtomicU64::new(0),
+        };
+        FrameSealer {
+            key,
+            frames: sealing(Channel::Frames),
+            control: sealing(Channel::Control),
+        }
+    }
+
+    /// Consegna la chiave di sessione al receiver che ha inviato `receiver_public_key`.
+    pub fn grant(&self, receiver_public_key: [u8; 32], passphrase: Option<&str>) -> Result<KeyGrant, CryptoError> {
+        let secret = EphemeralSecret::random_from_rng(OsRng);
+        let caster_public_key = PublicKey::from(&secret).to_bytes();
+        let shared = secret.diffie_hellman(&PublicKey::from(receiver_public_key));
+        if !shared.was_contributory() {
+            return Err(CryptoError::KeyExchange);
+        }
+        let wrapping = wrapping_cipher(
+            shared.as_bytes(),
+            passphrase,
+            &receiver_public_key,
+            &caster_public_key,
+        );
+        // La chiave di cifratura è nuova a ogni scambio: il nonce nullo non si ripete
+        let wrapped_key = wrapping
+            .encrypt(&Nonce::default(), self.key.as_slice())
+            .map_err(|_| CryptoError::KeyExchange)?;
+        Ok(KeyGrant { caster_public_key, wrapped_key })
+    }
+
+    /// Restituisce contatore e dati cifrati con la chiave del flusso, autenticati insieme al contatore.
+    pub fn seal(&self, channel: Channel, frame: &[u8]) -> Result<Vec<u8>, CryptoError> {
+        let sealing = match channel {
+            Channel::Frames => &self.frames,
+            Channel::Control => &self.control,
+        };
+        let counter = sealing.counter.fetch_add(1, Ordering::Relaxed).to_be_bytes();
+        let ciphertext = sealing
+            .cipher
+            .encrypt(&nonce(counter), Payload { msg: frame, aad: &counter })
+            .map_err(|_| CryptoError::Tampered)?;
+        let mut sealed = Vec::with_capacity(COUNTER_SIZE + ciphertext.len());
+        sealed.extend_from_slice(&counter);
+        sealed.extend(ciphertext);
+        Ok(sealed)
+    }
+}
+
+impl Default for FrameSealer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// La chiave di sessione non deve finire nei log
+impl fmt::Debug for FrameSealer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FrameSealer")
+            .field("frames", &self.frames.counter)
+            .field("control", &self.control.counter)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Decifra un flusso sul receiver, rifiutando i pacchetti alterati o già ricevuti.
+pub struct FrameOpener {
+    cipher: ChaCha20Poly1305,
+    highest: Option<u64>, // Contatore più alto accettato finora
+    seen: u64, // Bit i: accettato il frame `highest - i`
+}
+
+impl FrameOpener {
+    pub fn new(key: [u8; KEY_SIZE], channel: Channel) -> Self {
+        FrameOpener {
+            cipher: channel_cipher(&key, channel),
+            highest: None,
+            seen: 0,
+        }
+    }
+
+    pub fn open(&mut self, sealed: &[u8]) -> Result<Vec<u8>, CryptoError> {
+        if sealed.len() < COUNTER_SIZE {
+            return Err(CryptoError::Truncated);
+        }
+        let (counter_bytes, ciphertext) = sealed.split_at(COUNTER_SIZE);
+        let counter_bytes: [u8; COUNTER_SIZE] = counter_bytes.try_into().unwrap();
+        let counter = u64::from_be_bytes(counter_bytes);
+        if self.already_seen(counter) {
+            return Err(CryptoError::Replayed(counter));
+        }
+        let frame = self
+            .cipher
+            .decrypt(&nonce(counter_bytes), Payload { msg: ciphertext, aad: &counter_bytes })
+            .map_err(|_| CryptoError::Tampered)?;
+        // La finestra si aggiorna solo per i frame autentici
+        self.mark_seen(counter);
+        Ok(frame)
+    }
+
+    fn already_seen(&self, counter: u64) -> bool {
+        match self.highest {
+            None => false,
+            Some(highest) if counter > highest => false,
+            Some(highest) => {
+                let age = highest - counter;
+                age >= REPLAY_WINDOW || self.seen & (1 << age) != 0
+            }
+        }
+    }
+
+    fn mark_seen(&mut self, counter: u64) {
+        match self.highest {
+            Some(highest) if counter <= highest => self.seen |= 1 << (highest - counter),
+            Some(highest) => {
+                let shift = counter - highest;
+                self.seen = if shift >= REPLAY_WINDOW { 0 } else { self.seen << shift };
+                self.seen |= 1;
+                self.highest = Some(counter);
+            }
+            None => {
+                self.seen = 1;
+                self.highest = Some(counter);
+            }
+        }
+    }
+}
+
+impl fmt::Debug for FrameOpener {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FrameOpener")
+            .field("highest", &self.highest)
+            .finish_non_exhaustive()
+    }
+}
+
+fn nonce(counter: [u8; COUNTER_SIZE]) -> Nonce {
+    let mut nonce = Nonce::default();
+    nonce[4..].copy_from_slice(&counter);
+    nonce
+}
+
+// Chiave di un flusso, derivata dalla chiave di sessione
+fn channel_cipher(session_key: &[u8; KEY_SIZE], channel: Channel) -> ChaCha20Poly1305 {
+    let hkdf = Hkdf::<Sha256>::new(None, session_key);
+    let mut key = [0; KEY_SIZE];
+    hkdf.expand(channel.info(), &mut key).expect("32 byte sono una lunghezza valida per HKDF-SHA256");
+    ChaCha20Poly1305::new(Key::from_slice(&key))
+}
+
+// Chiave con cui il caster cifra la chiave di sessione per un receiver. La passphrase,
+// se impostata, entra nella derivazione: senza di essa non si ricava la chiave.
+fn wrapping_cipher(
+    shared: &[u8; 32],
+    passphrase: Option<&str>,
+    receiver_public_key: &[u8; 32],
+    caster_public_key: &[u8; 32],
+) -> ChaCha20Poly1305 {
+    let hkdf = Hkdf::<Sha256>::new(passphrase.map(str::as_bytes), shared);
+    let mut info = KEY_WRAP_INFO.to_vec();
+    info.extend_from_slice(receiver_public_key);
+    info.extend_from_slice(caster_public_key);
+    let mut key = [0; KEY_SIZE];
+    hkdf.expand(&info, &mut key).expect("32 byte sono una lunghezza valida per HKDF-SHA256");
+    ChaCha20Poly1305::new(Key::from_slice(&key))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session() -> (FrameSealer, [u8; KEY_SIZE]) {
+        let sealer = FrameSealer::new();
+        let exchange = KeyExchange::new();
+        let grant = sealer.grant(exchange.public_key(), Some("482913")).unwrap();
+        let key = exchange.accept(&grant, Some("482913")).unwrap();
+        (sealer, key)
+    }
+
+    #[test]
+    fn the_session_key_needs_the_passphrase() {
+        let sealer = FrameSealer::new();
+        let exchange = KeyExchange::new();
+        let grant = sealer.grant(exchange.public_key(), Some("482913")).unwrap();
+        assert!(matches!(exchange.accept(&grant, Some("000000")), Err(CryptoError::KeyExchange)));
+        assert!(matches!(exchange.accept(&grant, None), Err(CryptoError::KeyExchange)));
+        assert_eq!(exchange.accept(&grant, Some("482913")).unwrap(), sealer.key);
+
+        // Un'altra richiesta di registrazione non decifra la risposta a questa
+        assert!(KeyExchange::new().accept(&grant, Some("482913")).is_err());
+    }
+
+    #[test]
+    fn frames_open_once_even_out_of_order() {
+        let (sealer, key) = session();
+        let mut opener = FrameOpener::new(key, Channel::Frames);
+        let first = sealer.seal(Channel::Frames, b"primo").unwrap();
+        let second = sealer.seal(Channel::Frames, b"secondo").unwrap();
+
+        assert_eq!(opener.open(&second).unwrap(), b"secondo");
+        assert_eq!(opener.open(&first).unwrap(), b"primo");
+        assert!(matches!(opener.open(&first), Err(CryptoError::Replayed(0))));
+
+        let mut tampered = sealer.seal(Channel::Frames, b"terzo").unwrap();
+        *tampered.last_mut().unwrap() ^= 1;
+        assert!(matches!(opener.open(&tampered), Err(CryptoError::Tampered)));
+        assert!(matches!(opener.open(&tampered[..4]), Err(CryptoError::Truncated)));
+    }
+
+    #[test]
+    fn tampered_counters_and_stale_frames_are_rejected() {
+        let (sealer, key) = session();
+        let mut opener = FrameOpener::new(key, Channel::Frames);
+        let sealed = sealer.seal(Channel::Frames, b"frame").unwrap();
+
+        // Anche il contatore è autenticato, e i tentativi falliti non lo consumano
+        let mut tampered = sealed.clone();
+        tampered[COUNTER_SIZE - 1] ^= 1;
+        assert!(matches!(opener.open(&tampered), Err(CryptoError::Tampered)));
+        assert_eq!(opener.open(&sealed).unwrap(), b"frame");
+
+        // Oltre la finestra un frame non ancora visto viene comunque rifiutato
+        let late = sealer.seal(Channel::Frames, b"in ritardo").unwrap();
+        for _ in 0..REPLAY_WINDOW {
+            sealer.seal(Channel::Frames, b"x").unwrap();
+        }
+        assert!(opener.open(&sealer.seal(Channel::Frames, b"ultimo").unwrap()).is_ok());
+        assert!(matches!(opener.open(&late), Err(CryptoError::Replayed(1))));
+    }
+
+    #[test]
+    fn channels_do_not_open_each_other() {
+        let (sealer, key) = session();
+        let control = sealer.seal(Channel::Control, b"pausa").unwrap();
+        let frame = sealer.seal(Channel::Frames, b"frame").unwrap();
+
+        // Stesso contatore su entrambi i flussi, ma chiavi diverse
+        assert_eq!(control[..COUNTER_SIZE], frame[..COUNTER_SIZE]);
+        assert!(matches!(
+            FrameOpener::new(key, Channel::Frames).open(&control),
+            Err(CryptoError::Tampered)
+        ));
+        assert_eq!(FrameOpener::new(key, Channel::Control).open(&control).unwrap(), b"pausa");
+    }
+}
@@ -0,0 +1,270 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, SocketAddrV4};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+use local_ip_address::list_afinet_netifas;
+use serde::{Deserialize, Serialize};
+use socket2::{Domain, Protocol, SockRef, Socket, Type};
+use tokio::net::UdpSocket;
+
+use crate::socket::socket::{PROTOCOL_MAGIC, PROTOCOL_VERSION};
+use crate::socket::transport::TransportKind;
+
+/// Gruppo multicast (administratively scoped) su cui i caster annunciano le sessioni attive.
+pub const DISCOVERY_GROUP: SocketAddrV4 = SocketAddrV4::new(Ipv4Addr::new(239, 255, 83, 68), 7880);
+/// Ogni quanto il caster annuncia la propria sessione.
+pub const ANNOUNCE_INTERVAL: Duration = Duration::from_secs(1);
+/// Dopo quanto tempo senza annunci una sessione sparisce dall'elenco.
+pub const SESSION_TIMEOUT: Duration = Duration::from_secs(3);
+/// Dimensione del buffer per gli annunci ricevuti.
+const ANNOUNCEMENT_BUFFER_SIZE: usize = 1024;
+
+/// Annuncio periodico di una sessione, inviato dal caster al gruppo di discovery.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Announcement {
+    magic: u32,
+    version: u8,
+    pub session_name: String,
+    pub port: u16, // Porta su cui il caster accetta le registrazioni
+    pub transport: TransportKind,
+    pub viewers: u32,
+    pub passphrase_required: bool,
+}
+
+impl Announcement {
+    pub fn new(
+        session_name: String,
+        port: u16,
+        transport: TransportKind,
+        viewers: u32,
+        passphrase_required: bool,
+    ) -> Self {
+        Announcement {
+            magic: PROTOCOL_MAGIC,
+            version: PROTOCOL_VERSION,
+            session_name,
+            port,
+            transport,
+            viewers,
+            passphrase_required,
+        }
+    }
+}
+
+/// Sessione scoperta sulla rete locale, come mostrata al receiver.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiscoveredSession {
+    pub address: SocketAddr, // Mittente dell'annuncio con la porta annunciata
+    pub session_name: String,
+    pub transport: TransportKind,
+    pub viewers: u32,
+    pub passphrase_required: bool,
+    last_seen: Instant,
+}
+
+impl fmt::Display for DiscoveredSession {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} - {} ({}, {} viewer{}{})",
+            self.session_name,
+            self.address,
+            self.transport,
+            self.viewers,
+            if self.viewers == 1 { "" } else { "s" },
+            if self.passphrase_required { ", PIN" } else { "" }
+        )
+    }
+}
+
+/// Lato caster della discovery: invia gli annunci al gruppo.
+#[derive(Debug)]
+pub struct Announcer {
+    socket: UdpSocket,
+    group: SocketAddrV4,
+}
+
+impl Announcer {
+    /// Gli annunci escono dall'interfaccia `interface`, o da quella predefinita se non specificata.
+    pub async fn bind(interface: Ipv4Addr, group: SocketAddrV4) -> io::Result<Self> {
+        let socket = UdpSocket::bind((interface, 0)).await?;
+        if !interface.is_unspecified() {
+            SockRef::from(&socket).set_multicast_if_v4(&interface)?;
+        }
+        // Anche i receiver sullo stesso host devono vedere la sessione
+        socket.set_multicast_loop_v4(true)?;
+        Ok(Announcer { socket, group })
+    }
+
+    pub async fn announce(&self, announcement: &Announcement) -> io::Result<()> {
+        let data = bincode::serialize(announcement)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        self.socket.send_to(&data, self.group).await.map(|_| ())
+    }
+}
+
+/// Lato receiver della discovery: raccoglie gli annunci finché non viene distrutto.
+#[derive(Debug)]
+pub struct DiscoveryBrowser {
+    sessions: Arc<RwLock<HashMap<SocketAddr, DiscoveredSession>>>,
+    task: tokio::task::JoinHandle<()>,
+}
+
+impl DiscoveryBrowser {
+    /// Si unisce al gruppo su tutte le interfacce IPv4 e avvia l'ascolto degli annunci.
+    pub fn start(group: SocketAddrV4) -> io::Result<Self> {
+        let socket = join_group(group)?;
+        let sessions = Arc::new(RwLock::new(HashMap::new()));
+        let task = tokio::spawn(listen(socket, sessions.clone()));
+        Ok(DiscoveryBrowser { sessions, task })
+    }
+
+    /// Sessioni annunciate di recente, ordinate per nome.
+    pub fn sessions(&self) -> Vec<DiscoveredSession> {
+        let mut sessions: Vec<DiscoveredSession> = self
+            .sessions
+            .read()
+            .unwrap()
+            .values()
+            .filter(|session| session.last_seen.elapsed() < SESSION_TIMEOUT)
+            .cloned()
+            .collect();
+        sessions.sort_by(|a, b| a.session_name.cmp(&b.session_name).then(a.address.cmp(&b.address)));
+        sessions
+    }
+}
+
+impl Drop for DiscoveryBrowser {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+fn join_group(group: SocketAddrV4) -> io::Result<UdpSocket> {
+    let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
+    // Più receiver sullo stesso host ascoltano sulla stessa porta
+    socket.set_reuse_address(true)?;
+    #[cfg(any(
+        target_os = "macos",
+        target_os = "ios",
+        target_os = "freebsd",
+        target_os = "openbsd",
+        target_os = "netbsd",
+        target_os = "dragonfly"
+    ))]
+    socket.set_reuse_port(true)?;
+    socket.bind(&SocketAddr::from((Ipv4Addr::UNSPECIFIED, group.port())).into())?;
+    // Gli annunci arrivano dall'interfaccia scelta dal caster: ci si unisce su tutte
+    let interfaces: Vec<Ipv4Addr> = list_afinet_netifas()
+        .map(|interfaces| {
+            interfaces
+                .into_iter()
+                .filter_map(|(_, ip)| match ip {
+                    IpAddr::V4(ip) => Some(ip),
+                    IpAddr::V6(_) => None,
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    let joined = interfaces
+        .iter()
+        .filter(|interface| socket.join_multicast_v4(group.ip(), interface).is_ok())
+        .count();
+    if joined == 0 {
+        socket.join_multicast_v4(group.ip(), &Ipv4Addr::UNSPECIFIED)?;
+    }
+    socket.set_nonblocking(true)?;
+    UdpSocket::from_std(socket.into())
+}
+
+async fn listen(socket: UdpSocket, sessions: Arc<RwLock<HashMap<SocketAddr, DiscoveredSession>>>) {
+    let mut buffer = [0u8; ANNOUNCEMENT_BUFFER_SIZE];
+    loop {
+        let (len, from) = match socket.recv_from(&mut buffer).await {
+            Ok(received) => received,
+            Err(e) => {
+                eprintln!("Errore durante la ricezione degli annunci: {}", e);
+                break;
+            }
+        };
+        // Annunci estranei o di versioni diverse del protocollo vengono ignorati
+        let Ok(announcement) = bincode::deserialize::<Announcement>(&buffer[..len]) else {
+            continue;
+        };
+        if announcement.magic != PROTOCOL_MAGIC || announcement.version != PROTOCOL_VERSION {
+            continue;
+        }
+        let address = SocketAddr::new(from.ip(), announcement.port);
+        let mut sessions = sessions.write().unwrap();
+        sessions.retain(|_, session| session.last_seen.elapsed() < SESSION_TIMEOUT);
+        sessions.insert(
+            address,
+            DiscoveredSession {
+                address,
+                session_name: announcement.session_name,
+                transport: announcement.transport,
+                viewers: announcement.viewers,
+                passphrase_required: announcement.passphrase_required,
+                last_seen: Instant::now(),
+            },
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::socket::test_util::RECV_TIMEOUT;
+
+    // Gruppo diverso da quello reale: il test non vede i caster attivi sulla rete
+    const TEST_GROUP: SocketAddrV4 = SocketAddrV4::new(Ipv4Addr::new(239, 255, 83, 69), 47880);
+
+    // Ripete l'annuncio finché il browser non elenca almeno una sessione
+    async fn discovered(
+        announcer: &Announcer,
+        browser: &DiscoveryBrowser,
+        announcement: &Announcement,
+    ) -> Vec<DiscoveredSession> {
+        tokio::time::timeout(RECV_TIMEOUT, async {
+            loop {
+                announcer.announce(announcement).await.unwrap();
+                let sessions = browser.sessions();
+                if !sessions.is_empty() {
+                    return sessions;
+                }
+                tokio::time::sleep(Duration::from_millis(20)).await;
+            }
+        })
+        .await
+        .expect("nessun annuncio ricevuto")
+    }
+
+    #[tokio::test]
+    async fn announcements_are_listed_as_sessions() {
+        let browser = DiscoveryBrowser::start(TEST_GROUP).unwrap();
+        // La porta del gruppo è condivisa tra più receiver sullo stesso host
+        let second = DiscoveryBrowser::start(TEST_GROUP).unwrap();
+        let announcer = Announcer::bind(Ipv4Addr::LOCALHOST, TEST_GROUP).await.unwrap();
+
+        // Un annuncio di un'altra versione del protocollo non compare nell'elenco
+        let mut foreign = Announcement::new("Vecchio caster".into(), 7000, TransportKind::Udp, 0, false);
+        foreign.version = PROTOCOL_VERSION + 1;
+        announcer.announce(&foreign).await.unwrap();
+
+        let announcement = Announcement::new("Aula 3".into(), 7878, TransportKind::Tcp, 2, true);
+        let sessions = discovered(&announcer, &browser, &announcement).await;
+        assert_eq!(sessions.len(), 1);
+        let session = &sessions[0];
+        assert_eq!(session.address, SocketAddr::from((Ipv4Addr::LOCALHOST, 7878)));
+        assert_eq!(session.session_name, "Aula 3");
+        assert_eq!(session.transport, TransportKind::Tcp);
+        assert_eq!(session.viewers, 2);
+        assert!(session.passphrase_required);
+        assert_eq!(session.to_string(), "Aula 3 - 127.0.0.1:7878 (TCP, 2 viewers, PIN)");
+        let seen_by_second = discovered(&announcer, &second, &announcement).await;
+        assert_eq!(seen_by_second[0].address, session.address);
+    }
+}
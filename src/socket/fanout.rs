@@ -0,0 +1,217 @@
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use tokio::sync::{watch, Notify};
+use tokio::task::JoinHandle;
+
+use crate::socket::pacing::Pacer;
+use crate::socket::socket::KEYFRAME_REQUEST_INTERVAL;
+use crate::socket::transport::{CasterChannel, CasterTransport};
+
+/// Frame pronti per l'invio, impacchettati una sola volta e condivisi tra le code.
+pub type QueuedFrame = Arc<Vec<Vec<u8>>>;
+
+/// Frame in coda per ogni receiver oltre i quali si scartano i più vecchi.
+pub const SEND_QUEUE_CAPACITY: usize = 3;
+
+#[derive(Debug)]
+struct QueueState {
+    frames: Mutex<VecDeque<QueuedFrame>>,
+    ready: Notify, // Segnala al task di invio che c'è un frame in coda
+    dropped: AtomicU64,
+    needs_keyframe: AtomicBool, // I delta non sono decodificabili fino al prossimo keyframe
+    last_keyframe_request: Mutex<Option<Instant>>,
+}
+
+impl Default for QueueState {
+    fn default() -> Self {
+        QueueState {
+            frames: Mutex::new(VecDeque::new()),
+            ready: Notify::new(),
+            dropped: AtomicU64::new(0),
+            // Un nuovo receiver non ha ancora un frame di riferimento
+            needs_keyframe: AtomicBool::new(true),
+            last_keyframe_request: Mutex::new(None),
+        }
+    }
+}
+
+/// Coda di invio di un receiver, svuotata da un task dedicato: un receiver lento
+/// accumula ritardo solo sulla propria coda, senza rallentare la cattura né gli altri.
+#[derive(Debug)]
+pub struct SendQueue {
+    state: Arc<QueueState>,
+    task: JoinHandle<()>,
+}
+
+impl SendQueue {
+    /// Avvia il task che invia a `address` i frame accodati, al ritmo concesso da `pacer`,
+    /// fino alla terminazione della socket.
+    pub fn start(
+        address: String,
+        transport: Arc<Option<CasterChannel>>,
+        pacer: Arc<Pacer>,
+        termination_rx: watch::Receiver<bool>,
+    ) -> Self {
+        let state = Arc::new(QueueState::default());
+        let task = tokio::spawn(drain(address, transport, pacer, state.clone(), termination_rx));
+        SendQueue { state, task }
+    }
+
+    /// Accoda un frame, scartando il più vecchio se la coda è piena. Dopo uno scarto i delta
+    /// non sono decodificabili: vengono saltati finché non arriva un keyframe.
+    ///
+    /// Restituisce true se va chiesto un keyframe all'encoder, al più una volta per
+    /// KEYFRAME_REQUEST_INTERVAL.
+    pub fn push(&self, frame: QueuedFrame, keyframe: bool) -> bool {
+        if keyframe {
+            self.state.needs_keyframe.store(false, Ordering::Relaxed);
+        } else if self.state.needs_keyframe.load(Ordering::Relaxed) {
+            self.state.dropped.fetch_add(1, Ordering::Relaxed);
+            return self.keyframe_request_due();
+        }
+
+        let dropped = {
+            let mut frames = self.state.frames.lock().unwrap();
+            let dropped = frames.len() >= SEND_QUEUE_CAPACITY && frames.pop_front().is_some();
+            frames.push_back(frame);
+            dropped
+        };
+        self.state.ready.notify_one();
+        if !dropped {
+            return false;
+        }
+        self.state.dropped.fetch_add(1, Ordering::Relaxed);
+        if keyframe {
+            return false;
+        }
+        self.state.needs_keyframe.store(true, Ordering::Relaxed);
+        self.keyframe_request_due()
+    }
+
+    fn keyframe_request_due(&self) -> bool {
+        let mut last_request = self.state.last_keyframe_request.lock().unwrap();
+        if last_request.is_some_and(|last| last.elapsed() < KEYFRAME_REQUEST_INTERVAL) {
+            return false;
+        }
+        *last_request = Some(Instant::now());
+        true
+    }
+
+    /// Frame in attesa di invio.
+    pub fn depth(&self) -> usize {
+        self.state.frames.lock().unwrap().len()
+    }
+
+    /// Frame scartati perché il receiver non teneva il passo, compresi i delta saltati dopo uno scarto.
+    pub fn dropped(&self) -> u64 {
+        self.state.dropped.load(Ordering::Relaxed)
+    }
+}
+
+// Il task termina insieme alla coda, cioè quando il receiver viene rimosso
+impl Drop for SendQueue {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+async fn drain(
+    address: String,
+    transport: Arc<Option<CasterChannel>>,
+    pacer: Arc<Pacer>,
+    state: Arc<QueueState>,
+    mut termination_rx: watch::Receiver<bool>,
+) {
+    let Some(transport) = transport.as_ref() else {
+        return;
+    };
+    loop {
+        let next = state.frames.lock().unwrap().pop_front();
+        match next {
+            Some(packets) => {
+                for packet in packets.iter() {
+                    pacer.wait(packet.len()).await;
+                    if let Err(e) = transport.send_packets(&address, std::slice::from_ref(packet)).await {
+                        eprintln!("Errore durante l'invio del frame a {}: {}", address, e);
+                        break;
+                    }
+                }
+            }
+            None => tokio::select! {
+                _ = state.ready.notified() => {}
+                // Socket terminata o distrutta del tutto
+                result = termination_rx.changed() => {
+                    if result.is_err() || *termination_rx.borrow() {
+                        break;
+                    }
+                }
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::socket::fec::Redundancy;
+    use crate::socket::pacing::MaxBandwidth;
+    use crate::socket::test_util::{loopback, pattern, recv_frame};
+    use crate::socket::transport::TransportKind;
+
+    fn frame(id: u8) -> QueuedFrame {
+        Arc::new(vec![vec![id]])
+    }
+
+    // Coda senza trasporto: il task di invio termina subito e i frame restano in coda
+    fn stalled_queue() -> SendQueue {
+        let (_, termination_rx) = watch::channel(false);
+        let pacer = Arc::new(Pacer::new(MaxBandwidth::Unlimited));
+        SendQueue::start("127.0.0.1:9".to_string(), Arc::new(None), pacer, termination_rx)
+    }
+
+    #[tokio::test]
+    async fn deltas_wait_for_a_keyframe_after_a_drop() {
+        let queue = stalled_queue();
+
+        // Un nuovo receiver salta i delta e chiede un keyframe, una volta sola
+        assert!(queue.push(frame(0), false));
+        assert!(!queue.push(frame(1), false));
+        assert_eq!((queue.depth(), queue.dropped()), (0, 2));
+
+        assert!(!queue.push(frame(2), true));
+        assert!(!queue.push(frame(3), false));
+        assert!(!queue.push(frame(4), false));
+        assert_eq!((queue.depth(), queue.dropped()), (SEND_QUEUE_CAPACITY, 2));
+
+        // Coda piena: il frame più vecchio viene scartato e i delta successivi saltati,
+        // ma la richiesta precedente è troppo recente per ripeterla
+        assert!(!queue.push(frame(5), false));
+        assert!(!queue.push(frame(6), false));
+        assert_eq!((queue.depth(), queue.dropped()), (SEND_QUEUE_CAPACITY, 4));
+
+        std::thread::sleep(KEYFRAME_REQUEST_INTERVAL);
+        assert!(queue.push(frame(7), false));
+
+        // Il keyframe fa ripartire l'invio dei delta
+        assert!(!queue.push(frame(8), true));
+        assert!(!queue.push(frame(9), false));
+        let queued: Vec<u8> = queue.state.frames.lock().unwrap().iter().map(|frame| frame[0][0]).collect();
+        assert_eq!(queued, vec![5, 8, 9]);
+    }
+
+    #[tokio::test]
+    async fn queued_frames_reach_the_receiver() {
+        let (caster, receiver, from, _) = loopback(TransportKind::Udp).await;
+        let data = pattern(5000);
+        let packets = caster.packetize(1, &data, Redundancy::Off);
+        let (_termination_tx, termination_rx) = watch::channel(false);
+        let pacer = Arc::new(Pacer::new(MaxBandwidth::Unlimited));
+        let queue = SendQueue::start(from, Arc::new(Some(caster)), pacer, termination_rx);
+
+        assert!(!queue.push(Arc::new(packets), true));
+        assert_eq!(recv_frame(&receiver).await, data);
+    }
+}
@@ -0,0 +1,283 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use reed_solomon_erasure::galois_8::ReedSolomon;
+
+use crate::socket::socket::MAX_CHUNK_PAYLOAD;
+
+/// Dimensione dei parametri FEC in testa al payload di ogni chunk di parità.
+pub const PARITY_HEADER_SIZE: usize = 8;
+/// Dati per chunk quando la FEC è attiva: il chunk di parità deve stare in un datagramma.
+pub const FEC_CHUNK_PAYLOAD: usize = MAX_CHUNK_PAYLOAD - PARITY_HEADER_SIZE;
+/// Chunk di dati protetti da ogni gruppo di parità.
+pub const FEC_GROUP_SIZE: usize = 20;
+
+/// Quota di chunk di parità aggiunti ai chunk di dati di ogni frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Redundancy {
+    /// Nessuna parità: la perdita di un chunk fa perdere il frame
+    #[default]
+    Off,
+    /// Chunk di parità in percentuale dei chunk di dati di un gruppo
+    Percent(u8),
+}
+
+impl Redundancy {
+    /// Ridondanze selezionabili nelle impostazioni del caster.
+    pub const PRESETS: [Redundancy; 5] = [
+        Redundancy::Off,
+        Redundancy::Percent(5),
+        Redundancy::Percent(10),
+        Redundancy::Percent(20),
+        Redundancy::Percent(50),
+    ];
+
+    /// Chunk di parità per un gruppo di `group_size` chunk di dati: almeno uno se attiva.
+    /// Oltre il 100% la parità non aggiunge protezione, quindi la percentuale viene limitata.
+    pub fn parity_count(&self, group_size: usize) -> usize {
+        match self {
+            Redundancy::Off => 0,
+            Redundancy::Percent(percent) => {
+                (group_size * (*percent).min(100) as usize).div_ceil(100).max(1)
+            }
+        }
+    }
+}
+
+impl fmt::Display for Redundancy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Redundancy::Off => write!(f, "No FEC"),
+            Redundancy::Percent(percent) => write!(f, "{}% FEC", percent),
+        }
+    }
+}
+
+/// Parametri con cui il receiver ricostruisce i gruppi di un frame.
+///
+/// Layout (big-endian): group_size (2) | parity_count (2) | frame_len (4).
+/// Il chunk di parità `i` protegge il gruppo `i / parity_count`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParityHeader {
+    pub group_size: u16,
+    pub parity_count: u16,
+    pub frame_len: u32, // Serve a ritagliare l'ultimo chunk, più corto degli altri
+}
+
+impl ParityHeader {
+    pub fn encode(&self, payload: &mut Vec<u8>) {
+        payload.extend(&self.group_size.to_be_bytes());
+        payload.extend(&self.parity_count.to_be_bytes());
+        payload.extend(&self.frame_len.to_be_bytes());
+    }
+
+    /// Separa i parametri dai dati di parità; None se il payload non è valido.
+    pub fn decode(payload: &[u8]) -> Option<(ParityHeader, &[u8])> {
+        if payload.len() <= PARITY_HEADER_SIZE {
+            return None;
+        }
+        let header = ParityHeader {
+            group_size: u16::from_be_bytes([payload[0], payload[1]]),
+            parity_count: u16::from_be_bytes([payload[2], payload[3]]),
+            frame_len: u32::from_be_bytes([payload[4], payload[5], payload[6], payload[7]]),
+        };
+        if header.group_size == 0 || header.parity_count == 0 {
+            return None;
+        }
+        Some((header, &payload[PARITY_HEADER_SIZE..]))
+    }
+
+    // Indici dei chunk di dati del gruppo `group`
+    fn data_range(&self, group: u32, chunk_count: u32) -> std::ops::Range<u32> {
+        let first = group.saturating_mul(self.group_size as u32).min(chunk_count);
+        first..first.saturating_add(self.group_size as u32).min(chunk_count)
+    }
+
+    // Indici dei chunk di parità del gruppo `group`
+    fn parity_range(&self, group: u32) -> std::ops::Range<u32> {
+        let first = group.saturating_mul(self.parity_count as u32);
+        first..first.saturating_add(self.parity_count as u32)
+    }
+}
+
+/// Calcola i payload dei chunk di parità (parametri inclusi) per i chunk di un frame,
+/// tagliati a `FEC_CHUNK_PAYLOAD` byte. Sono ordinati per indice di parità.
+pub fn parity_chunks(chunks: &[&[u8]], frame_len: usize, redundancy: Redundancy) -> Vec<Vec<u8>> {
+    let group_size = FEC_GROUP_SIZE.min(chunks.len());
+    let parity_count = redundancy.parity_count(group_size);
+    if group_size == 0 || parity_count == 0 {
+        return Vec::new();
+    }
+    let header = ParityHeader {
+        group_size: group_size as u16,
+        parity_count: parity_count as u16,
+        frame_len: frame_len as u32,
+    };
+
+    let mut parity = Vec::new();
+    for group in chunks.chunks(group_size) {
+        // I chunk del gruppo vengono allineati al più lungo con degli zeri
+        let shard_len = group.iter().map(|chunk| chunk.len()).max().unwrap_or(0);
+        let mut shards: Vec<Vec<u8>> = group
+            .iter()
+            .map(|chunk| {
+                let mut shard = chunk.to_vec();
+                shard.resize(shard_len, 0);
+                shard
+            })
+            .chain((0..parity_count).map(|_| vec![0; shard_len]))
+            .collect();
+        let encoded = ReedSolomon::new(group.len(), parity_count).and_then(|rs| rs.encode(&mut shards));
+        if let Err(e) = encoded {
+            eprintln!("Errore durante il calcolo della parità: {:?}", e);
+            return Vec::new();
+        }
+        for shard in shards.drain(group.len()..) {
+            let mut payload = Vec::with_capacity(PARITY_HEADER_SIZE + shard.len());
+            header.encode(&mut payload);
+            payload.extend(shard);
+            parity.push(payload);
+        }
+    }
+    parity
+}
+
+/// Ricostruisce i chunk di dati mancanti del gruppo `group`, se ne sono arrivati abbastanza.
+/// `parity` contiene i dati di parità senza parametri. Restituisce indice e dati dei chunk recuperati.
+pub fn recover_group(
+    header: &ParityHeader,
+    group: u32,
+    chunk_count: u32,
+    chunks: &HashMap<u32, Vec<u8>>,
+    parity: &HashMap<u32, Vec<u8>>,
+) -> Vec<(u32, Vec<u8>)> {
+    let data_range = header.data_range(group, chunk_count);
+    let parity_range = header.parity_range(group);
+    let missing: Vec<u32> = data_range.clone().filter(|i| !chunks.contains_key(i)).collect();
+    let available_parity = parity_range.clone().filter(|i| parity.contains_key(i)).count();
+    if missing.is_empty() || available_parity < missing.len() {
+        return Vec::new();
+    }
+    let Some(shard_len) = parity_range.clone().find_map(|i| parity.get(&i)).map(Vec::len) else {
+        return Vec::new();
+    };
+
+    let mut shards: Vec<Option<Vec<u8>>> = Vec::with_capacity(data_range.len() + parity_range.len());
+    for i in data_range.clone() {
+        match chunks.get(&i) {
+            // Un chunk più lungo della parità non appartiene a questo gruppo
+            Some(chunk) if chunk.len() > shard_len => return Vec::new(),
+            Some(chunk) => {
+                let mut shard = chunk.clone();
+                shard.resize(shard_len, 0);
+                shards.push(Some(shard));
+            }
+            None => shards.push(None),
+        }
+    }
+    for i in parity_range {
+        shards.push(parity.get(&i).filter(|shard| shard.len() == shard_len).cloned());
+    }
+
+    let rebuilt = ReedSolomon::new(data_range.len(), header.parity_count as usize)
+        .and_then(|rs| rs.reconstruct_data(&mut shards));
+    if rebuilt.is_err() {
+        return Vec::new();
+    }
+    missing
+        .into_iter()
+        .filter_map(|i| {
+            let mut chunk = shards[(i - data_range.start) as usize].take()?;
+            // L'ultimo chunk del frame era più corto: si rimuovono gli zeri aggiunti
+            if i == chunk_count - 1 {
+                let offset = (chunk_count as usize - 1) * FEC_CHUNK_PAYLOAD;
+                chunk.truncate((header.frame_len as usize).checked_sub(offset)?);
+            } else {
+                chunk.truncate(FEC_CHUNK_PAYLOAD);
+            }
+            Some((i, chunk))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Divide il frame in chunk come il caster quando la FEC è attiva
+    fn split(frame: &[u8]) -> (HashMap<u32, Vec<u8>>, Vec<Vec<u8>>) {
+        let chunks: Vec<&[u8]> = frame.chunks(FEC_CHUNK_PAYLOAD).collect();
+        let map = chunks.iter().enumerate().map(|(i, chunk)| (i as u32, chunk.to_vec())).collect();
+        (map, chunks.iter().map(|chunk| chunk.to_vec()).collect())
+    }
+
+    #[test]
+    fn parity_count_rounds_up() {
+        assert_eq!(Redundancy::Off.parity_count(20), 0);
+        assert_eq!(Redundancy::Percent(5).parity_count(20), 1);
+        assert_eq!(Redundancy::Percent(10).parity_count(3), 1);
+        assert_eq!(Redundancy::Percent(50).parity_count(20), 10);
+    }
+
+    #[test]
+    fn parity_count_never_exceeds_the_group_size() {
+        assert_eq!(Redundancy::Percent(100).parity_count(FEC_GROUP_SIZE), FEC_GROUP_SIZE);
+        assert_eq!(Redundancy::Percent(255).parity_count(FEC_GROUP_SIZE), FEC_GROUP_SIZE);
+        assert_eq!(Redundancy::Percent(200).parity_count(3), 3);
+    }
+
+    #[test]
+    fn parity_header_roundtrip() {
+        let header = ParityHeader { group_size: 20, parity_count: 2, frame_len: 123_456 };
+        let mut payload = Vec::new();
+        header.encode(&mut payload);
+        payload.extend([1, 2, 3]);
+        assert_eq!(ParityHeader::decode(&payload), Some((header, &[1u8, 2, 3][..])));
+        assert_eq!(ParityHeader::decode(&payload[..PARITY_HEADER_SIZE]), None);
+        payload[0..2].copy_from_slice(&0u16.to_be_bytes());
+        assert_eq!(ParityHeader::decode(&payload), None);
+    }
+
+    #[test]
+    fn recovers_lost_chunks_in_each_group() {
+        // Due gruppi, l'ultimo chunk è più corto degli altri
+        let frame: Vec<u8> = (0..FEC_CHUNK_PAYLOAD * 25 + 17).map(|i| (i * 7) as u8).collect();
+        let (mut received, chunks) = split(&frame);
+        let chunk_refs: Vec<&[u8]> = chunks.iter().map(Vec::as_slice).collect();
+        let payloads = parity_chunks(&chunk_refs, frame.len(), Redundancy::Percent(10));
+        assert_eq!(payloads.len(), 4);
+
+        let mut parity = HashMap::new();
+        let mut header = None;
+        for (i, payload) in payloads.iter().enumerate() {
+            let (decoded, data) = ParityHeader::decode(payload).unwrap();
+            header = Some(decoded);
+            parity.insert(i as u32, data.to_vec());
+        }
+        let header = header.unwrap();
+        let chunk_count = chunks.len() as u32;
+
+        received.remove(&3);
+        received.remove(&(chunk_count - 1));
+        for group in 0..2 {
+            for (i, chunk) in recover_group(&header, group, chunk_count, &received, &parity) {
+                received.insert(i, chunk);
+            }
+        }
+        let rebuilt: Vec<u8> = (0..chunk_count).flat_map(|i| received[&i].clone()).collect();
+        assert_eq!(rebuilt, frame);
+    }
+
+    #[test]
+    fn too_many_losses_are_not_recovered() {
+        let frame = vec![5u8; FEC_CHUNK_PAYLOAD * 10];
+        let (mut received, chunks) = split(&frame);
+        let chunk_refs: Vec<&[u8]> = chunks.iter().map(Vec::as_slice).collect();
+        let payloads = parity_chunks(&chunk_refs, frame.len(), Redundancy::Percent(10));
+        let (header, data) = ParityHeader::decode(&payloads[0]).unwrap();
+        let parity = HashMap::from([(0, data.to_vec())]);
+        received.remove(&0);
+        received.remove(&1);
+        assert!(recover_group(&header, 0, 10, &received, &parity).is_empty());
+    }
+}
@@ -0,0 +1,268 @@
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::socket::reassembly::ReceiverStats;
+
+/// Ogni quanto il receiver invia al caster un report sulla qualità del collegamento.
+pub const REPORT_INTERVAL: Duration = Duration::from_secs(1);
+
+// Soglie di adattamento della qualità
+const DEGRADE_LOSS: f64 = 0.10;
+const DEGRADE_RTT_MS: u32 = 300;
+const UPGRADE_LOSS: f64 = 0.02;
+const UPGRADE_RTT_MS: u32 = 100;
+const UPGRADE_AFTER_REPORTS: u32 = 3; // Report buoni consecutivi prima di salire di livello
+
+/// Report periodico del receiver: frame ricevuti e persi dall'ultimo report,
+/// più il timestamp dell'ultimo frame ricevuto per stimare il round-trip.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default)]
+pub struct FeedbackReport {
+    pub frames_received: u64,
+    pub frames_dropped: u64,
+    pub echo_timestamp_ms: u32, // Timestamp del caster dell'ultimo frame ricevuto
+    pub hold_ms: u32,           // Tempo trascorso tra la ricezione di quel frame e il report
+}
+
+/// Livello di qualità con cui il caster serve un receiver.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+pub enum QualityLevel {
+    #[default]
+    High,
+    Medium,
+    Low,
+    Minimal,
+}
+
+impl QualityLevel {
+    /// Percentuale della risoluzione in uscita inviata a questo livello.
+    pub fn scale_percent(&self) -> u32 {
+        match self {
+            QualityLevel::High => 100,
+            QualityLevel::Medium => 75,
+            QualityLevel::Low => 50,
+            QualityLevel::Minimal => 25,
+        }
+    }
+
+    /// Livello di compressione zlib/deflate (1 = veloce, 9 = massima).
+    pub fn compression_level(&self) -> u32 {
+        match self {
+            QualityLevel::High => 1,
+            QualityLevel::Medium => 6,
+            QualityLevel::Low | QualityLevel::Minimal => 9,
+        }
+    }
+
+    /// Viene inviato un frame ogni `frame_interval` catturati.
+    pub fn frame_interval(&self) -> u64 {
+        match self {
+            QualityLevel::High | QualityLevel::Medium => 1,
+            QualityLevel::Low => 2,
+            QualityLevel::Minimal => 4,
+        }
+    }
+
+    /// Percentuale del bitrate H.264 scelto dal caster.
+    pub fn bitrate_percent(&self) -> u32 {
+        match self {
+            QualityLevel::High => 100,
+            QualityLevel::Medium => 60,
+            QualityLevel::Low => 35,
+            QualityLevel::Minimal => 20,
+        }
+    }
+
+    fn lower(&self) -> QualityLevel {
+        match self {
+            QualityLevel::High => QualityLevel::Medium,
+            QualityLevel::Medium => QualityLevel::Low,
+            QualityLevel::Low | QualityLevel::Minimal => QualityLevel::Minimal,
+        }
+    }
+
+    fn higher(&self) -> QualityLevel {
+        match self {
+            QualityLevel::High | QualityLevel::Medium => QualityLevel::High,
+            QualityLevel::Low => QualityLevel::Medium,
+            QualityLevel::Minimal => QualityLevel::Low,
+        }
+    }
+}
+
+/// Stato di adattamento del caster per un singolo receiver.
+#[derive(Debug, Clone, Default)]
+pub struct ReceiverQuality {
+    level: QualityLevel,
+    good_reports: u32,
+    rtt_ms: Option<u32>,
+    loss: f64, // Frazione di frame persi nell'ultimo report
+}
+
+impl ReceiverQuality {
+    pub fn level(&self) -> QualityLevel {
+        self.level
+    }
+
+    pub fn loss(&self) -> f64 {
+        self.loss
+    }
+
+    /// Aggiorna il livello in base a un report; restituisce true se è cambiato.
+    /// `now_ms` è l'orologio del caster usato per i timestamp dei frame.
+    pub fn on_report(&mut self, report: &FeedbackReport, now_ms: u32) -> bool {
+        let total = report.frames_received + report.frames_dropped;
+        if total == 0 {
+            return false;
+        }
+        let loss = report.frames_dropped as f64 / total as f64;
+        self.loss = loss;
+        let rtt_ms = now_ms
+            .wrapping_sub(report.echo_timestamp_ms)
+            .saturating_sub(report.hold_ms);
+        self.rtt_ms = Some(rtt_ms);
+
+        let previous = self.level;
+        if loss > DEGRADE_LOSS || rtt_ms > DEGRADE_RTT_MS {
+            self.level = self.level.lower();
+            self.good_reports = 0;
+        } else if loss < UPGRADE_LOSS && rtt_ms < UPGRADE_RTT_MS {
+            self.good_reports += 1;
+            if self.good_reports >= UPGRADE_AFTER_REPORTS {
+                self.level = self.level.higher();
+                self.good_reports = 0;
+            }
+        } else {
+            self.good_reports = 0;
+        }
+        self.level != previous
+    }
+}
+
+/// Lato receiver: tiene traccia di cosa è già stato riportato al caster.
+#[derive(Debug)]
+pub struct FeedbackTracker {
+    last_report: Instant,
+    reported_received: u64,
+    reported_dropped: u64,
+    last_timestamp: Option<(u32, Instant)>,
+}
+
+impl Default for FeedbackTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FeedbackTracker {
+    pub fn new() -> Self {
+        FeedbackTracker {
+            last_report: Instant::now(),
+            reported_received: 0,
+            reported_dropped: 0,
+            last_timestamp: None,
+        }
+    }
+
+    pub fn frame_received(&mut self, timestamp_ms: u32) {
+        self.last_timestamp = Some((timestamp_ms, Instant::now()));
+    }
+
+    /// Restituisce il report da inviare se è trascorso `REPORT_INTERVAL` dall'ultimo.
+    pub fn report_if_due(&mut self, stats: &ReceiverStats) -> Option<FeedbackReport> {
+        if self.last_report.elapsed() < REPORT_INTERVAL {
+            return None;
+        }
+        // Senza frame ricevuti il caster non ha un timestamp con cui stimare il ritardo
+        let (echo_timestamp_ms, received_at) = self.last_timestamp?;
+        self.last_report = Instant::now();
+
+        let (received, dropped) = (stats.frames_received(), stats.frames_dropped());
+        let report = FeedbackReport {
+            frames_received: received - self.reported_received,
+            frames_dropped: dropped - self.reported_dropped,
+            echo_timestamp_ms,
+            hold_ms: received_at.elapsed().as_millis() as u32,
+        };
+        self.reported_received = received;
+        self.reported_dropped = dropped;
+        Some(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report(frames_received: u64, frames_dropped: u64, echo_timestamp_ms: u32) -> FeedbackReport {
+        FeedbackReport {
+            frames_received,
+            frames_dropped,
+            echo_timestamp_ms,
+            hold_ms: 0,
+        }
+    }
+
+    #[test]
+    fn losses_and_delay_lower_the_quality() {
+        let mut quality = ReceiverQuality::default();
+        assert_eq!(quality.level(), QualityLevel::High);
+
+        // 20% di frame persi
+        assert!(quality.on_report(&report(80, 20, 1000), 1010));
+        assert_eq!(quality.level(), QualityLevel::Medium);
+
+        // Nessuna perdita ma round-trip oltre la soglia
+        assert!(quality.on_report(&report(100, 0, 2000), 2000 + DEGRADE_RTT_MS + 1));
+        assert_eq!(quality.level(), QualityLevel::Low);
+
+        assert!(quality.on_report(&report(10, 90, 3000), 3010));
+        assert!(!quality.on_report(&report(10, 90, 4000), 4010));
+        assert_eq!(quality.level(), QualityLevel::Minimal);
+    }
+
+    #[test]
+    fn quality_rises_only_after_consecutive_good_reports() {
+        let mut quality = ReceiverQuality::default();
+        quality.on_report(&report(50, 50, 0), 10);
+        assert_eq!(quality.level(), QualityLevel::Medium);
+
+        for _ in 0..UPGRADE_AFTER_REPORTS - 1 {
+            assert!(!quality.on_report(&report(100, 0, 0), 10));
+        }
+        // Un report intermedio azzera il conteggio
+        assert!(!quality.on_report(&report(95, 5, 0), 10));
+        for _ in 0..UPGRADE_AFTER_REPORTS - 1 {
+            assert!(!quality.on_report(&report(100, 0, 0), 10));
+        }
+        assert!(quality.on_report(&report(100, 0, 0), 10));
+        assert_eq!(quality.level(), QualityLevel::High);
+
+        // Un report senza frame non cambia nulla
+        assert!(!quality.on_report(&report(0, 0, 0), 10_000));
+    }
+
+    #[test]
+    fn round_trip_excludes_the_receiver_hold_time() {
+        let mut quality = ReceiverQuality::default();
+        let held = FeedbackReport {
+            hold_ms: 900,
+            ..report(100, 0, u32::MAX - 50)
+        };
+        // L'orologio del caster è ripartito da zero: 1000 ms trascorsi, 900 passati sul receiver
+        assert!(!quality.on_report(&held, 949));
+        assert_eq!(quality.rtt_ms, Some(100));
+    }
+
+    #[test]
+    fn lower_levels_send_less() {
+        let mut previous = QualityLevel::High;
+        for level in [QualityLevel::Medium, QualityLevel::Low, QualityLevel::Minimal] {
+            assert!(level.scale_percent() < previous.scale_percent());
+            assert!(level.bitrate_percent() < previous.bitrate_percent());
+            assert!(level.compression_level() >= previous.compression_level());
+            assert!(level.frame_interval() >= previous.frame_interval());
+            previous = level;
+        }
+    }
+}
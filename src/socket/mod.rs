@@ -1 +1,13 @@
-pub mod socket;
\ No newline at end of file
+pub mod socket;
+pub mod reassembly;
+pub mod compression;
+pub mod feedback;
+pub mod transport;
+pub mod crypto;
+pub mod discovery;
+pub mod session_uri;
+pub mod fanout;
+pub mod pacing;
+pub mod fec;
+#[cfg(test)]
+pub mod test_util;
@@ -0,0 +1,146 @@
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::socket::socket::MAX_PAYLOAD;
+
+/// Finestra di traffico che il pacer lascia partire senza attese.
+const BURST_DURATION: Duration = Duration::from_millis(5);
+
+/// Banda massima in uscita dal caster, condivisa da tutti i receiver.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MaxBandwidth {
+    /// Nessun limite: i pacchetti partono appena pronti
+    #[default]
+    Unlimited,
+    /// Megabit al secondo
+    Mbps(u32),
+}
+
+impl MaxBandwidth {
+    /// Limiti selezionabili nelle impostazioni del caster.
+    pub const PRESETS: [MaxBandwidth; 7] = [
+        MaxBandwidth::Mbps(5),
+        MaxBandwidth::Mbps(10),
+        MaxBandwidth::Mbps(20),
+        MaxBandwidth::Mbps(50),
+        MaxBandwidth::Mbps(100),
+        MaxBandwidth::Mbps(200),
+        MaxBandwidth::Unlimited,
+    ];
+
+    /// Byte al secondo, 0 se illimitata.
+    pub fn bytes_per_second(&self) -> u64 {
+        match self {
+            MaxBandwidth::Unlimited => 0,
+            MaxBandwidth::Mbps(mbps) => *mbps as u64 * 1_000_000 / 8,
+        }
+    }
+}
+
+impl fmt::Display for MaxBandwidth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MaxBandwidth::Unlimited => write!(f, "Unlimited"),
+            MaxBandwidth::Mbps(mbps) => write!(f, "{} Mbps", mbps),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64, // Byte disponibili, negativi se si è in debito
+    refilled_at: Instant,
+}
+
+/// Token bucket che distribuisce nel tempo i pacchetti di un frame, invece di
+/// inviarli tutti insieme saturando i buffer della socket e della rete.
+///
+/// Il caster ha un solo pacer, condiviso dalle code di tutti i receiver: il limite
+/// vale per il traffico complessivo, quindi con più receiver ognuno ne riceve una parte.
+#[derive(Debug)]
+pub struct Pacer {
+    rate: AtomicU64, // Byte al secondo, 0 se illimitata
+    bucket: Mutex<Bucket>,
+}
+
+impl Pacer {
+    pub fn new(max_bandwidth: MaxBandwidth) -> Self {
+        Pacer {
+            rate: AtomicU64::new(max_bandwidth.bytes_per_second()),
+            bucket: Mutex::new(Bucket {
+                tokens: 0.0,
+                refilled_at: Instant::now(),
+            }),
+        }
+    }
+
+    pub fn set_max_bandwidth(&self, max_bandwidth: MaxBandwidth) {
+        self.rate.store(max_bandwidth.bytes_per_second(), Ordering::Relaxed);
+    }
+
+    /// Attende finché `bytes` possono essere inviati senza superare la banda massima.
+    pub async fn wait(&self, bytes: usize) {
+        let rate = self.rate.load(Ordering::Relaxed);
+        if rate == 0 {
+            return;
+        }
+        let delay = {
+            let mut bucket = self.bucket.lock().unwrap();
+            let now = Instant::now();
+            // Il burst contiene almeno un datagramma intero
+            let burst = (rate as f64 * BURST_DURATION.as_secs_f64()).max(MAX_PAYLOAD as f64);
+            let elapsed = now.duration_since(bucket.refilled_at).as_secs_f64();
+            bucket.tokens = (bucket.tokens + elapsed * rate as f64).min(burst);
+            bucket.refilled_at = now;
+            // I byte si prenotano subito: le attese di più code si accodano senza sovrapporsi
+            bucket.tokens -= bytes as f64;
+            Duration::from_secs_f64((-bucket.tokens).max(0.0) / rate as f64)
+        };
+        if !delay.is_zero() {
+            tokio::time::sleep(delay).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bandwidth_is_unlimited_by_default() {
+        assert_eq!(MaxBandwidth::default(), MaxBandwidth::Unlimited);
+        assert_eq!(MaxBandwidth::Unlimited.bytes_per_second(), 0);
+        assert_eq!(MaxBandwidth::Mbps(8).bytes_per_second(), 1_000_000);
+    }
+
+    #[tokio::test]
+    async fn unlimited_pacer_never_waits() {
+        let pacer = Pacer::new(MaxBandwidth::Unlimited);
+        let started = Instant::now();
+        for _ in 0..1000 {
+            pacer.wait(MAX_PAYLOAD).await;
+        }
+        assert!(started.elapsed() < Duration::from_millis(50));
+    }
+
+    #[tokio::test]
+    async fn limited_pacer_spreads_the_traffic() {
+        // 1 MB/s: 100 KB partono in circa 100 ms, meno il burst iniziale
+        let pacer = Pacer::new(MaxBandwidth::Mbps(8));
+        let started = Instant::now();
+        for _ in 0..10 {
+            pacer.wait(10_000).await;
+        }
+        let elapsed = started.elapsed();
+        assert!(elapsed >= Duration::from_millis(80), "{:?}", elapsed);
+        assert!(elapsed < Duration::from_secs(1), "{:?}", elapsed);
+
+        // Il limite si toglie anche a sessione avviata
+        pacer.set_max_bandwidth(MaxBandwidth::Unlimited);
+        let started = Instant::now();
+        pacer.wait(1_000_000).await;
+        assert!(started.elapsed() < Duration::from_millis(50));
+    }
+}
@@ -0,0 +1,236 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::socket::fec::{self, ParityHeader};
+use crate::socket::socket::{PacketHeader, PayloadKind};
+
+/// Tempo massimo di attesa dei pacchetti mancanti di un frame.
+pub const DEFAULT_REASSEMBLY_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Contatori condivisi tra la socket del receiver e la UI.
+#[derive(Debug, Default)]
+pub struct ReceiverStats {
+    frames_received: AtomicU64,
+    frames_dropped: AtomicU64,
+    chunks_recovered: AtomicU64,
+    packets_rejected: AtomicU64,
+    caster_fps: AtomicU32,
+}
+
+impl ReceiverStats {
+    pub fn frames_received(&self) -> u64 {
+        self.frames_received.load(Ordering::Relaxed)
+    }
+
+    pub fn frames_dropped(&self) -> u64 {
+        self.frames_dropped.load(Ordering::Relaxed)
+    }
+
+    /// Chunk persi e ricostruiti grazie alla parità.
+    pub fn chunks_recovered(&self) -> u64 {
+        self.chunks_recovered.load(Ordering::Relaxed)
+    }
+
+    /// Datagrammi scartati perché estranei al protocollo, di un'altra versione o corrotti.
+    pub fn packets_rejected(&self) -> u64 {
+        self.packets_rejected.load(Ordering::Relaxed)
+    }
+
+    pub fn packet_rejected(&self) {
+        self.packets_rejected.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Frame rate misurato dal caster e ricevuto insieme ai frame.
+    pub fn caster_fps(&self) -> u32 {
+        self.caster_fps.load(Ordering::Relaxed)
+    }
+
+    pub fn set_caster_fps(&self, fps: u32) {
+        self.caster_fps.store(fps, Ordering::Relaxed);
+    }
+
+    pub fn frame_completed(&self) {
+        self.frames_received.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+#[derive(Debug)]
+struct PartialFrame {
+    chunk_count: u32,
+    chunks: HashMap<u32, Vec<u8>>,
+    parity: HashMap<u32, Vec<u8>>, // Dati di parità, senza parametri
+    fec: Option<ParityHeader>, // Noti dal primo chunk di parità ricevuto
+    first_seen: Instant,
+}
+
+/// Ricompone i frame a partire dai pacchetti UDP, che possono arrivare
+/// persi, duplicati o fuori ordine. Ogni frame ha il proprio buffer,
+/// indicizzato dal frame id dell'header.
+#[derive(Debug)]
+pub struct FrameReassembler {
+    frames: HashMap<u32, PartialFrame>,
+    timeout: Duration,
+    last_completed: Option<u32>,
+    stats: Arc<ReceiverStats>,
+}
+
+impl FrameReassembler {
+    pub fn new(timeout: Duration, stats: Arc<ReceiverStats>) -> Self {
+        FrameReassembler {
+            frames: HashMap::new(),
+            timeout,
+            last_completed: None,
+            stats,
+        }
+    }
+
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    pub fn stats(&self) -> &ReceiverStats {
+        &self.stats
+    }
+
+    /// Aggiunge un pacchetto e restituisce i dati del frame se è stato completato.
+    pub fn push(&mut self, header: &PacketHeader, payload: &[u8]) -> Option<Vec<u8>> {
+        self.expire(Instant::now());
+
+        // Pacchetto di un frame già superato da uno più recente: scartalo
+        if let Some(last) = self.last_completed {
+            if !is_newer(header.frame_id, last) {
+                return None;
+            }
+        }
+
+        let frame = self
+            .frames
+            .entry(header.frame_id)
+            .or_insert_with(|| PartialFrame {
+                chunk_count: header.chunk_count,
+                chunks: HashMap::new(),
+                parity: HashMap::new(),
+                fec: None,
+                first_seen: Instant::now(),
+            });
+        if frame.chunk_count != header.chunk_count {
+            return None;
+        }
+        // Gruppo FEC a cui appartiene il pacchetto, se il frame ha parità
+        let group = if header.kind == PayloadKind::Parity {
+            let (fec, parity) = ParityHeader::decode(payload)?;
+            frame.fec = Some(fec);
+            frame.parity.insert(header.chunk_index, parity.to_vec());
+            Some(header.chunk_index / fec.parity_count as u32)
+        } else {
+            frame.chunks.insert(header.chunk_index, payload.to_vec());
+            frame.fec.map(|fec| header.chunk_index / fec.group_size as u32)
+        };
+        if let (Some(fec), Some(group)) = (frame.fec, group) {
+            if frame.chunks.len() < frame.chunk_count as usize {
+                let recovered = fec::recover_group(&fec, group, frame.chunk_count, &frame.chunks, &frame.parity);
+                self.stats.chunks_recovered.fetch_add(recovered.len() as u64, Ordering::Relaxed);
+                frame.chunks.extend(recovered);
+            }
+        }
+        if frame.chunks.len() < frame.chunk_count as usize {
+            return None;
+        }
+
+        let mut frame = self.frames.remove(&header.frame_id)?;
+        let mut data = Vec::new();
+        for i in 0..frame.chunk_count {
+            data.extend(frame.chunks.remove(&i)?);
+        }
+
+        // I frame più vecchi di quello appena completato non verranno più mostrati
+        let stale = self
+            .frames
+            .keys()
+            .filter(|id| !is_newer(**id, header.frame_id))
+            .count();
+        self.frames.retain(|id, _| is_newer(*id, header.frame_id));
+        self.stats.frames_dropped.fetch_add(stale as u64, Ordering::Relaxed);
+
+        self.last_completed = Some(header.frame_id);
+        self.stats.frame_completed();
+        Some(data)
+    }
+
+    /// Scarta i frame incompleti che attendono da più del timeout.
+    pub fn expire(&mut self, now: Instant) {
+        let timeout = self.timeout;
+        let before = self.frames.len();
+        self.frames
+            .retain(|_, frame| now.duration_since(frame.first_seen) < timeout);
+        let expired = before - self.frames.len();
+        if expired > 0 {
+            self.stats.frames_dropped.fetch_add(expired as u64, Ordering::Relaxed);
+        }
+    }
+}
+
+// Confronto tra frame id che tiene conto del wrap-around del contatore
+fn is_newer(frame_id: u32, reference: u32) -> bool {
+    (frame_id.wrapping_sub(reference) as i32) > 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::socket::test_util::data_packets;
+
+    #[test]
+    fn reassembles_out_of_order_and_drops_older_frames() {
+        let stats = Arc::new(ReceiverStats::default());
+        let mut reassembler = FrameReassembler::new(DEFAULT_REASSEMBLY_TIMEOUT, stats.clone());
+        let chunks: Vec<Vec<u8>> = (0..4).map(|i| vec![i as u8; 10]).collect();
+        let old = data_packets(0, &chunks);
+        let new = data_packets(1, &chunks);
+
+        assert!(reassembler.push(&old[0].0, &old[0].1).is_none());
+        let mut completed = None;
+        for i in [3, 1, 0, 2] {
+            completed = reassembler.push(&new[i].0, &new[i].1);
+        }
+        assert_eq!(completed, Some(chunks.concat()));
+        assert_eq!(stats.frames_received(), 1);
+        // Il frame 0 era incompleto quando è arrivato il frame 1
+        assert_eq!(stats.frames_dropped(), 1);
+        // Un chunk arrivato in ritardo non riapre il frame
+        assert!(reassembler.push(&old[1].0, &old[1].1).is_none());
+    }
+
+    #[test]
+    fn duplicate_chunks_do_not_complete_a_frame() {
+        let mut reassembler =
+            FrameReassembler::new(DEFAULT_REASSEMBLY_TIMEOUT, Arc::new(ReceiverStats::default()));
+        let chunks: Vec<Vec<u8>> = (0..3).map(|i| vec![i as u8; 5]).collect();
+        let packets = data_packets(7, &chunks);
+        for _ in 0..3 {
+            assert!(reassembler.push(&packets[0].0, &packets[0].1).is_none());
+        }
+        assert!(reassembler.push(&packets[2].0, &packets[2].1).is_none());
+        assert_eq!(reassembler.push(&packets[1].0, &packets[1].1), Some(chunks.concat()));
+    }
+
+    #[test]
+    fn expired_frames_are_counted_as_dropped() {
+        let stats = Arc::new(ReceiverStats::default());
+        let mut reassembler = FrameReassembler::new(Duration::from_millis(10), stats.clone());
+        let packets = data_packets(3, &[vec![1; 5], vec![2; 5]]);
+        assert!(reassembler.push(&packets[0].0, &packets[0].1).is_none());
+        reassembler.expire(Instant::now() + Duration::from_millis(20));
+        assert_eq!(stats.frames_dropped(), 1);
+    }
+
+    #[test]
+    fn frame_ids_wrap_around() {
+        assert!(is_newer(0, u32::MAX));
+        assert!(is_newer(5, 3));
+        assert!(!is_newer(3, 5));
+        assert!(!is_newer(u32::MAX, 0));
+    }
+}
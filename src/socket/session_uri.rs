@@ -0,0 +1,151 @@
+use std::fmt;
+use std::str::FromStr;
+
+use thiserror::Error;
+use url::Url;
+
+use crate::socket::socket::DEFAULT_PORT;
+use crate::socket::transport::TransportKind;
+
+/// Schema degli URI con cui il caster condivide la sessione.
+pub const URI_SCHEME: &str = "screencast";
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum UriError {
+    #[error("Not a valid URI: {0}")]
+    Malformed(String),
+    #[error("Unsupported scheme {0} (expected screencast)")]
+    UnsupportedScheme(String),
+    #[error("The URI has no host")]
+    MissingHost,
+    #[error("Unknown transport {0}")]
+    UnknownTransport(String),
+}
+
+/// Parametri per connettersi a una sessione, nel formato
+/// `screencast://host:port?session=…&pin=…&transport=…`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SessionUri {
+    pub host: String, // IP (IPv6 tra parentesi quadre) o nome host
+    pub port: u16,
+    pub session: Option<String>,
+    pub pin: Option<String>,
+    pub transport: TransportKind,
+}
+
+impl SessionUri {
+    /// Indirizzo del caster nel formato accettato da `resolve_caster_address`.
+    pub fn address(&self) -> String {
+        format!("{}:{}", self.host, self.port)
+    }
+}
+
+impl fmt::Display for SessionUri {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut url = Url::parse(&format!("{}://{}", URI_SCHEME, self.address())).map_err(|_| fmt::Error)?;
+        {
+            let mut query = url.query_pairs_mut();
+            if let Some(session) = &self.session {
+                query.append_pair("session", session);
+            }
+            if let Some(pin) = &self.pin {
+                query.append_pair("pin", pin);
+            }
+            query.append_pair("transport", &self.transport.to_string().to_lowercase());
+        }
+        write!(f, "{}", url)
+    }
+}
+
+impl FromStr for SessionUri {
+    type Err = UriError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let url = Url::parse(s.trim()).map_err(|e| UriError::Malformed(e.to_string()))?;
+        if url.scheme() != URI_SCHEME {
+            return Err(UriError::UnsupportedScheme(url.scheme().to_string()));
+        }
+        // Host::to_string racchiude gli IPv6 tra parentesi quadre
+        let host = match url.host() {
+            Some(host) if !host.to_string().is_empty() => host.to_string(),
+            _ => return Err(UriError::MissingHost),
+        };
+
+        let mut uri = SessionUri {
+            host,
+            port: url.port().unwrap_or(DEFAULT_PORT),
+            session: None,
+            pin: None,
+            transport: TransportKind::default(),
+        };
+        for (key, value) in url.query_pairs() {
+            // I parametri vuoti equivalgono a quelli assenti, quelli sconosciuti vengono ignorati
+            if value.is_empty() {
+                continue;
+            }
+            match key.as_ref() {
+                "session" => uri.session = Some(value.into_owned()),
+                "pin" => uri.pin = Some(value.into_owned()),
+                "transport" => {
+                    uri.transport = TransportKind::ALL
+                        .into_iter()
+                        .find(|transport| transport.to_string().eq_ignore_ascii_case(&value))
+                        .ok_or_else(|| UriError::UnknownTransport(value.into_owned()))?;
+                }
+                _ => {}
+            }
+        }
+        Ok(uri)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn uris_round_trip() {
+        let uris = [
+            SessionUri {
+                host: "192.168.1.20".into(),
+                port: 9000,
+                session: Some("Aula 3 & co.".into()),
+                pin: Some("482913".into()),
+                transport: TransportKind::Multicast,
+            },
+            SessionUri {
+                host: "[fe80::1]".into(),
+                port: DEFAULT_PORT,
+                session: None,
+                pin: None,
+                transport: TransportKind::Tcp,
+            },
+        ];
+        for uri in uris {
+            assert_eq!(uri.to_string().parse::<SessionUri>().unwrap(), uri);
+        }
+    }
+
+    #[test]
+    fn missing_parameters_use_the_defaults() {
+        let uri: SessionUri = "screencast://caster.local?session=&foo=bar".parse().unwrap();
+        assert_eq!(uri.address(), format!("caster.local:{}", DEFAULT_PORT));
+        assert_eq!(uri.session, None);
+        assert_eq!(uri.pin, None);
+        assert_eq!(uri.transport, TransportKind::default());
+    }
+
+    #[test]
+    fn invalid_uris_are_rejected() {
+        assert!(matches!("192.168.1.20".parse::<SessionUri>(), Err(UriError::Malformed(_))));
+        assert_eq!(
+            "http://192.168.1.20".parse::<SessionUri>(),
+            Err(UriError::UnsupportedScheme("http".into()))
+        );
+        assert_eq!("screencast:///".parse::<SessionUri>(), Err(UriError::MissingHost));
+        assert_eq!(
+            "screencast://192.168.1.20?transport=quic".parse::<SessionUri>(),
+            Err(UriError::UnknownTransport("quic".into()))
+        );
+    }
+}
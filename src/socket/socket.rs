@@ -1,20 +1,209 @@
+use rand::RngCore;
 use serde::{Deserialize, Serialize};
-use std::{net::IpAddr, sync::Arc};
-use std::collections::HashMap;
-use tokio::{net::UdpSocket, sync::{watch, RwLock}};
-use xcap::image::RgbaImage;
+use sha2::{Digest, Sha256};
+use std::{fmt, net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4}, sync::Arc};
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::time::{Duration, Instant};
+use tokio::sync::{watch, RwLock};
+use subtle::ConstantTimeEq;
 use thiserror::Error;
+use crate::codec::video::VideoCodec;
+use crate::codec::EncodedFrame;
+use crate::socket::compression::Compression;
+use crate::socket::crypto::{Channel, FrameOpener, FrameSealer, KeyExchange, KeyGrant};
+use crate::socket::discovery::{Announcement, Announcer, ANNOUNCE_INTERVAL, DISCOVERY_GROUP};
+use crate::socket::fanout::{QueuedFrame, SendQueue};
+use crate::socket::fec::Redundancy;
+use crate::socket::pacing::{MaxBandwidth, Pacer};
+use crate::socket::feedback::{FeedbackReport, FeedbackTracker, QualityLevel, ReceiverQuality};
+use crate::socket::reassembly::ReceiverStats;
+use crate::socket::transport::{
+    CasterChannel, CasterTransport, Incoming, ReceiverChannel, ReceiverTransport, TransportEvent,
+    TransportKind,
+};
+
+/// Porta su cui il caster è in ascolto se non ne viene indicata un'altra.
+pub const DEFAULT_PORT: u16 = 7878;
+/// Ogni quanto il receiver segnala al caster di essere ancora attivo.
+pub const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(1);
+/// Dopo quanto tempo senza messaggi un receiver viene considerato disconnesso.
+pub const RECEIVER_TIMEOUT: Duration = Duration::from_secs(5);
+/// Intervallo minimo tra due richieste di keyframe dello stesso receiver.
+pub const KEYFRAME_REQUEST_INTERVAL: Duration = Duration::from_millis(250);
+/// Attesa massima della risposta del caster a una richiesta di registrazione.
+pub const REGISTRATION_TIMEOUT: Duration = Duration::from_secs(1);
+/// Tempo concesso al caster per approvare una richiesta, dopo il quale viene scartata.
+pub const APPROVAL_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Caratteri massimi di PIN e passphrase della sessione.
+pub const MAX_PASSPHRASE_LEN: usize = 64;
+/// Caratteri massimi del nome mostrato al caster nelle richieste di approvazione.
+pub const MAX_DISPLAY_NAME_LEN: usize = 64;
+
+const PASSPHRASE_PROOF_INFO: &[u8] = b"screencast passphrase proof";
 
 const MTU: usize = 1500; // Dimensione massima del pacchetto
 const UDP_HEADER_SIZE: usize = 8; // Dimensione dell'header UDP
 const IP_HEADER_SIZE: usize = 20; // Dimensione dell'header IP
-const MAX_PAYLOAD: usize = MTU - UDP_HEADER_SIZE - IP_HEADER_SIZE; // Spazio disponibile per il payload UDP
+pub const MAX_PAYLOAD: usize = MTU - UDP_HEADER_SIZE - IP_HEADER_SIZE; // Spazio disponibile per il payload UDP
+pub const MAX_CHUNK_PAYLOAD: usize = MAX_PAYLOAD - HEADER_SIZE; // Spazio per i dati dopo l'header del protocollo
+
+/// Magic number ("SCST") che apre ogni pacchetto del protocollo.
+pub const PROTOCOL_MAGIC: u32 = 0x5343_5354;
+/// Versione corrente del protocollo: i pacchetti con versione diversa vengono rifiutati.
+pub const PROTOCOL_VERSION: u8 = 8;
+/// Dimensione in byte dell'header serializzato.
+pub const HEADER_SIZE: usize = 24;
+
+/// Tipo di contenuto trasportato da un pacchetto.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum PayloadKind {
+    Frame = 0,
+    Control = 1, // Messaggio del caster a un singolo receiver, sempre in un solo pacchetto
+    Parity = 2, // Chunk di parità con cui ricostruire i chunk persi di un frame
+}
+
+impl TryFrom<u8> for PayloadKind {
+    type Error = ProtocolError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(PayloadKind::Frame),
+            1 => Ok(PayloadKind::Control),
+            2 => Ok(PayloadKind::Parity),
+            other => Err(ProtocolError::UnknownPayloadKind(other)),
+        }
+    }
+}
+
+/// Header di ogni datagramma inviato dal caster ai receiver.
+///
+/// Layout (big-endian): magic (4) | version (1) | kind (1) | payload_len (2) |
+/// frame_id (4) | chunk_index (4) | chunk_count (4) | checksum (4).
+/// Il checksum è il CRC32 del solo payload.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PacketHeader {
+    pub version: u8,
+    pub kind: PayloadKind,
+    pub payload_len: u16,
+    pub frame_id: u32,
+    pub chunk_index: u32,
+    pub chunk_count: u32,
+    pub checksum: u32,
+}
+
+impl PacketHeader {
+    pub fn new(
+        kind: PayloadKind,
+        frame_id: u32,
+        chunk_index: u32,
+        chunk_count: u32,
+        payload: &[u8],
+    ) -> Self {
+        PacketHeader {
+            version: PROTOCOL_VERSION,
+            kind,
+            payload_len: payload.len() as u16,
+            frame_id,
+            chunk_index,
+            chunk_count,
+            checksum: checksum(payload),
+        }
+    }
+
+    pub fn encode(&self, packet: &mut Vec<u8>) {
+        packet.extend(&PROTOCOL_MAGIC.to_be_bytes());
+        packet.push(self.version);
+        packet.push(self.kind as u8);
+        packet.extend(&self.payload_len.to_be_bytes());
+        packet.extend(&self.frame_id.to_be_bytes());
+        packet.extend(&self.chunk_index.to_be_bytes());
+        packet.extend(&self.chunk_count.to_be_bytes());
+        packet.extend(&self.checksum.to_be_bytes());
+    }
+
+    /// Valida e decodifica un datagramma, restituendo l'header e il payload.
+    pub fn decode(packet: &[u8]) -> Result<(PacketHeader, &[u8]), ProtocolError> {
+        if packet.len() < HEADER_SIZE {
+            return Err(ProtocolError::Truncated(packet.len()));
+        }
+        let read_u32 = |at: usize| u32::from_be_bytes([packet[at], packet[at + 1], packet[at + 2], packet[at + 3]]);
+
+        let magic = read_u32(0);
+        if magic != PROTOCOL_MAGIC {
+            return Err(ProtocolError::InvalidMagic(magic));
+        }
+        let version = packet[4];
+        if version != PROTOCOL_VERSION {
+            return Err(ProtocolError::UnsupportedVersion {
+                found: version,
+                expected: PROTOCOL_VERSION,
+            });
+        }
+        let kind = PayloadKind::try_from(packet[5])?;
+        let payload_len = u16::from_be_bytes([packet[6], packet[7]]);
+        let header = PacketHeader {
+            version,
+            kind,
+            payload_len,
+            frame_id: read_u32(8),
+            chunk_index: read_u32(12),
+            chunk_count: read_u32(16),
+            checksum: read_u32(20),
+        };
+
+        let payload = &packet[HEADER_SIZE..];
+        if payload.len() != payload_len as usize {
+            return Err(ProtocolError::Truncated(packet.len()));
+        }
+        if header.chunk_count == 0 || header.chunk_index >= header.chunk_count {
+            return Err(ProtocolError::InvalidChunk {
+                index: header.chunk_index,
+                count: header.chunk_count,
+            });
+        }
+        if checksum(payload) != header.checksum {
+            return Err(ProtocolError::ChecksumMismatch {
+                frame_id: header.frame_id,
+                chunk_index: header.chunk_index,
+            });
+        }
+        Ok((header, payload))
+    }
+}
+
+fn checksum(payload: &[u8]) -> u32 {
+    let mut crc = flate2::Crc::new();
+    crc.update(payload);
+    crc.sum()
+}
+
+#[derive(Error, Debug)]
+pub enum ProtocolError {
+    #[error("Packet too short ({0} bytes)")]
+    Truncated(usize),
+    #[error("Foreign packet: invalid magic number {0:#010x}")]
+    InvalidMagic(u32),
+    #[error("Unsupported protocol version {found} (expected {expected})")]
+    UnsupportedVersion { found: u8, expected: u8 },
+    #[error("Unknown payload kind {0}")]
+    UnknownPayloadKind(u8),
+    #[error("Invalid chunk {index} of {count}")]
+    InvalidChunk { index: u32, count: u32 },
+    #[error("Checksum mismatch in frame {frame_id}, chunk {chunk_index}")]
+    ChecksumMismatch { frame_id: u32, chunk_index: u32 },
+}
 
 #[derive(Serialize, Deserialize)]
 pub struct SerializableImage {
     width: u32,
     height: u32,
-    data: Vec<u8>,
+    compression: Compression,
+    fps: u32, // Frame rate misurato dal caster
+    timestamp_ms: u32, // Istante di invio secondo l'orologio del caster, rimandato nei report
+    data: Vec<u8>, // EncodedFrame serializzato e compresso con `compression`
 }
 impl SerializableImage {
   
@@ -29,25 +218,205 @@ impl SerializableImage {
     pub fn data(&self) -> &Vec<u8> {
         &self.data
     }
+
+    pub fn fps(&self) -> u32 {
+        self.fps
+    }
+
+    /// Decomprime e deserializza il frame codificato trasportato.
+    pub fn decode(&self) -> Result<EncodedFrame, Box<dyn std::error::Error + Send + Sync>> {
+        let decompressed = self.compression.decompress(&self.data)?;
+        Ok(bincode::deserialize(&decompressed)?)
+    }
+}
+
+/// Parametri dello stream comunicati ai receiver quando vengono accettati.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct StreamInfo {
+    pub width: u32,
+    pub height: u32,
+    pub codec: VideoCodec,
+    pub fps: u32,
+    pub session_name: String,
+    pub multicast_group: Option<SocketAddrV4>, // Gruppo da cui ricevere i frame, solo in multicast
+}
+
+impl fmt::Display for StreamInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} - {}x{} {} @ {} fps",
+            self.session_name, self.width, self.height, self.codec, self.fps
+        )
+    }
+}
+
+/// Stato della trasmissione, comunicato dal caster a ogni cambiamento.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StreamStatus {
+    #[default]
+    Live,
+    Paused,
+    Blanked,
+    Ended,
+}
+
+/// Messaggi di controllo inviati dal caster a un receiver.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub enum CasterMessage {
+    Accepted(StreamInfo, KeyGrant), // Con la chiave di sessione per decifrare i frame
+    Rejected(String),
+    InvalidPassphrase, // PIN o passphrase della sessione mancante o errata
+    AwaitingApproval, // La richiesta è in coda finché il caster non la approva
+    Paused,
+    Resumed,
+    Blanked,
+    SessionEnded,
+    Kicked, // Il caster ha disconnesso questo receiver
+}
+
+impl CasterMessage {
+    /// Stato della trasmissione annunciato dal messaggio, se ne annuncia uno.
+    pub fn status(&self) -> Option<StreamStatus> {
+        match self {
+            CasterMessage::Resumed => Some(StreamStatus::Live),
+            CasterMessage::Paused => Some(StreamStatus::Paused),
+            CasterMessage::Blanked => Some(StreamStatus::Blanked),
+            // Per il receiver disconnesso la sessione è finita
+            CasterMessage::SessionEnded | CasterMessage::Kicked => Some(StreamStatus::Ended),
+            CasterMessage::Accepted(..)
+            | CasterMessage::Rejected(_)
+            | CasterMessage::InvalidPassphrase
+            | CasterMessage::AwaitingApproval => None,
+        }
+    }
+}
+
+/// Involucro dei messaggi di controllo del caster. Quelli per i receiver registrati
+/// viaggiano cifrati con la chiave di controllo: un terzo non può chiudere o mettere
+/// in pausa lo stream fingendosi il caster.
+#[derive(Serialize, Deserialize, Debug)]
+enum ControlEnvelope {
+    Plain(CasterMessage), // Risposte alla registrazione: il receiver non ha ancora la chiave
+    Sealed(Vec<u8>),
+}
+
+impl From<StreamStatus> for CasterMessage {
+    fn from(status: StreamStatus) -> Self {
+        match status {
+            StreamStatus::Live => CasterMessage::Resumed,
+            StreamStatus::Paused => CasterMessage::Paused,
+            StreamStatus::Blanked => CasterMessage::Blanked,
+            StreamStatus::Ended => CasterMessage::SessionEnded,
+        }
+    }
+}
+
+/// Nome della sessione mostrato ai receiver: l'utente che trasmette, se noto.
+pub fn default_session_name() -> String {
+    std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .map(|user| format!("{}'s screen", user))
+        .unwrap_or_else(|_| "Screen casting".to_string())
+}
+
+/// Regole con cui il caster accetta le richieste di registrazione.
+#[derive(Clone, Debug, Default)]
+pub struct AccessPolicy {
+    pub passphrase: Option<String>, // Richiesta ai receiver, se impostata
+    pub require_approval: bool, // Ogni nuovo receiver va approvato a mano
+}
+
+/// Richiesta di registrazione in attesa dell'approvazione del caster.
+#[derive(Clone, Debug)]
+pub struct JoinRequest {
+    pub address: String,
+    pub display_name: Option<String>,
+    compression: Compression, // Negoziata alla richiesta, usata all'approvazione
+    public_key: [u8; 32], // Chiave X25519 del receiver per consegnargli la chiave di sessione
+    requested_at: Instant,
+}
+
+impl fmt::Display for JoinRequest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.display_name {
+            Some(name) => write!(f, "{} ({})", name, self.address),
+            None => write!(f, "{}", self.address),
+        }
+    }
+}
+
+/// Receiver registrato presso il caster.
+#[derive(Clone, Debug)]
+pub struct RegisteredReceiver {
+    address: String,
+    display_name: Option<String>,
+    compression: Compression, // Compressione negoziata alla registrazione
+    quality: ReceiverQuality, // Livello di qualità adattato in base ai report
+    joined_at: Instant,
+    last_seen: Instant, // Ultimo messaggio ricevuto (registrazione, heartbeat o report)
+    queue: Arc<SendQueue>, // Frame in attesa di invio a questo receiver
+}
+
+/// Receiver connesso, come mostrato nell'interfaccia del caster.
+#[derive(Clone, Debug)]
+pub struct ViewerInfo {
+    pub address: String,
+    pub display_name: Option<String>,
+    pub joined_at: Instant,
+    pub loss: f64, // Frazione di frame persi nell'ultimo report
+    pub queue_depth: usize, // Frame in coda di invio
+    pub dropped_frames: u64, // Frame scartati dalla coda perché il receiver è lento
+}
+
+impl From<&RegisteredReceiver> for ViewerInfo {
+    fn from(receiver: &RegisteredReceiver) -> Self {
+        ViewerInfo {
+            address: receiver.address.clone(),
+            display_name: receiver.display_name.clone(),
+            joined_at: receiver.joined_at,
+            loss: receiver.quality.loss(),
+            queue_depth: receiver.queue.depth(),
+            dropped_frames: receiver.queue.dropped(),
+        }
+    }
 }
 
 #[derive(Clone, Debug)]
 pub struct CasterSocket {
 //    ip_addr: String,
-    socket: Arc<Option<UdpSocket>>,
-    receiver_sockets: Arc<RwLock<Vec<String>>>,
+    transport: Arc<Option<CasterChannel>>,
+    receiver_sockets: Arc<RwLock<Vec<RegisteredReceiver>>>,
     termination_tx: watch::Sender<bool>, // Mittente del segnale di terminazione
     termination_rx: watch::Receiver<bool>, // Ricevitore del segnale di terminazione
-    notification_tx: watch::Sender<usize>, // Canale per notifiche
-
+    notification_tx: watch::Sender<Vec<ViewerInfo>>, // Receiver connessi, per l'interfaccia
+    next_frame_id: Arc<AtomicU32>, // Identificativo progressivo dei frame inviati
+    compression: Compression, // Compressione preferita dal caster
+    keyframe_requested: Arc<AtomicBool>, // Un nuovo receiver ha bisogno di un frame completo
+    fps: Arc<AtomicU32>, // Frame rate misurato, inoltrato ai receiver
+    started_at: Instant, // Origine dei timestamp dei frame
+    stream_info: Arc<std::sync::RwLock<StreamInfo>>, // Inviate ai receiver con l'accettazione
+    status: Arc<std::sync::RwLock<StreamStatus>>, // Stato corrente, inviato anche a chi si registra dopo
+    access: AccessPolicy,
+    join_requests: Arc<std::sync::RwLock<Vec<JoinRequest>>>, // Richieste in attesa di approvazione
+    banned: Arc<std::sync::RwLock<HashSet<IpAddr>>>, // Indirizzi esclusi per il resto della sessione
+    sealer: Arc<FrameSealer>, // Cifra frame e messaggi di controllo con la chiave di sessione
+    pacer: Arc<Pacer>, // Limita la banda complessiva in uscita
+    redundancy: Arc<std::sync::RwLock<Redundancy>>, // Chunk di parità aggiunti a ogni frame
 }
 
 impl CasterSocket {
 
-    pub async fn new(ip_addr: &str, notification_tx: watch::Sender<usize>) -> Self {
-        let socket = UdpSocket::bind(ip_addr).await.unwrap();
+    pub async fn new(
+        ip_addr: &str,
+        notification_tx: watch::Sender<Vec<ViewerInfo>>,
+        compression: Compression,
+        transport: TransportKind,
+        access: AccessPolicy,
+    ) -> std::io::Result<Self> {
+        let transport = CasterChannel::bind(ip_addr, transport).await?;
+        let multicast_group = transport.multicast_group();
         let receiver_sockets = Arc::new(RwLock::new(vec![]));
-        let socket_clone = Arc::new(Some(socket));
 
         let (termination_tx, termination_rx) = watch::channel(false); // Canale per terminazione
 
@@ -55,10 +424,30 @@ impl CasterSocket {
         let instance = CasterSocket {
             receiver_sockets,
             //ip_addr: ip_addr.to_string(), SERVE DAVVERO?? 
-            socket: socket_clone,
+            transport: Arc::new(Some(transport)),
             termination_tx,
             termination_rx,
             notification_tx,
+            next_frame_id: Arc::new(AtomicU32::new(0)),
+            compression,
+            keyframe_requested: Arc::new(AtomicBool::new(false)),
+            fps: Arc::new(AtomicU32::new(0)),
+            started_at: Instant::now(),
+            stream_info: Arc::new(std::sync::RwLock::new(StreamInfo {
+                width: 0,
+                height: 0,
+                codec: VideoCodec::default(),
+                fps: 0,
+                session_name: default_session_name(),
+                multicast_group,
+            })),
+            status: Arc::new(std::sync::RwLock::new(StreamStatus::Live)),
+            access,
+            join_requests: Arc::new(std::sync::RwLock::new(Vec::new())),
+            banned: Arc::new(std::sync::RwLock::new(HashSet::new())),
+            sealer: Arc::new(FrameSealer::new()),
+            pacer: Arc::new(Pacer::new(MaxBandwidth::default())),
+            redundancy: Arc::new(std::sync::RwLock::new(Redundancy::default())),
         };
 
         // Avvia il task per ascoltare le registrazioni
@@ -71,78 +460,432 @@ impl CasterSocket {
                 .await;
         });
 
-        instance
+        // Annuncia la sessione ai receiver della rete locale (il gruppo di discovery è IPv4)
+        if let Some(Ok(SocketAddr::V4(local))) = instance.transport.as_ref().as_ref().map(|t| t.local_addr()) {
+            let instance_clone = instance.clone();
+            tokio::spawn(async move { instance_clone.announce_session(local).await });
+        }
+
+        Ok(instance)
     }
 
-    pub async fn send_to_receivers(&self, frame: RgbaImage) {
-        if let Some(socket) = self.socket.as_ref() {
-            let serializable_image = SerializableImage {
-                width: frame.width(),
-                height: frame.height(),
-                data: frame.into_raw(),
-            };
+    /// Restituisce true (una sola volta) se serve inviare un keyframe.
+    pub fn take_keyframe_request(&self) -> bool {
+        self.keyframe_requested.swap(false, Ordering::Relaxed)
+    }
+
+    /// Aggiorna i parametri dello stream comunicati ai nuovi receiver.
+    pub fn update_stream_info(&self, width: u32, height: u32, codec: VideoCodec, fps: u32) {
+        let mut info = self.stream_info.write().unwrap();
+        info.width = width;
+        info.height = height;
+        info.codec = codec;
+        info.fps = fps;
+    }
+
+    /// Aggiorna lo stato della trasmissione e lo comunica a tutti i receiver se è cambiato.
+    pub async fn set_status(&self, status: StreamStatus) {
+        let previous = std::mem::replace(&mut *self.status.write().unwrap(), status);
+        if previous != status {
+            self.broadcast(&status.into()).await;
+        }
+    }
+
+    /// Richieste di registrazione in attesa, condivise con l'interfaccia del caster.
+    pub fn join_requests(&self) -> Arc<std::sync::RwLock<Vec<JoinRequest>>> {
+        self.join_requests.clone()
+    }
+
+    /// Approva una richiesta in attesa: il receiver inizia a ricevere lo stream.
+    pub async fn approve(&self, address: &str) {
+        if let Some(request) = self.take_join_request(address) {
+            self.accept_receiver(request).await;
+        }
+    }
+
+    /// Rifiuta una richiesta in attesa.
+    pub async fn deny(&self, address: &str) {
+        if let Some(request) = self.take_join_request(address) {
+            let reason = "The caster denied the request".to_string();
+            self.send_to_receiver(&request.address, &CasterMessage::Rejected(reason)).await;
+        }
+    }
+
+    /// Disconnette un receiver; può registrarsi di nuovo.
+    ///
+    /// La chiave di sessione non cambia: con il multicast il receiver disconnesso può
+    /// ancora decifrare i frame del gruppo, se continua a riceverli.
+    pub async fn kick(&self, address: &str) {
+        let mut receivers = self.receiver_sockets.write().await;
+        receivers.retain(|receiver| receiver.address != address);
+        self.notify_viewers(&receivers);
+        drop(receivers);
+        self.send_to_receiver(address, &CasterMessage::Kicked).await;
+    }
+
+    /// Disconnette un receiver e rifiuta il suo indirizzo IP fino alla fine della sessione.
+    /// Come per `kick`, la chiave di sessione non viene revocata.
+    pub async fn ban(&self, address: &str) {
+        if let Ok(address) = address.parse::<SocketAddr>() {
+            self.banned.write().unwrap().insert(address.ip());
+        }
+        self.kick(address).await;
+    }
+
+    fn is_banned(&self, address: &str) -> bool {
+        address
+            .parse::<SocketAddr>()
+            .is_ok_and(|address| self.banned.read().unwrap().contains(&address.ip()))
+    }
+
+    fn take_join_request(&self, address: &str) -> Option<JoinRequest> {
+        let mut requests = self.join_requests.write().unwrap();
+        let index = requests.iter().position(|request| request.address == address)?;
+        Some(requests.remove(index))
+    }
+
+    pub fn set_fps(&self, fps: u32) {
+        self.fps.store(fps, Ordering::Relaxed);
+    }
+
+    pub fn set_max_bandwidth(&self, max_bandwidth: MaxBandwidth) {
+        self.pacer.set_max_bandwidth(max_bandwidth);
+    }
+
+    pub fn set_redundancy(&self, redundancy: Redundancy) {
+        *self.redundancy.write().unwrap() = redundancy;
+    }
+
+    // Millisecondi trascorsi dalla creazione della socket, con wrap-around
+    fn now_ms(&self) -> u32 {
+        self.started_at.elapsed().as_millis() as u32
+    }
 
-            let serialized = bincode::serialize(&serializable_image).unwrap();
-            let total_packets = (serialized.len() + MAX_PAYLOAD - 8 - 1) / (MAX_PAYLOAD - 8);
+    /// Livelli di qualità richiesti dai receiver attualmente registrati.
+    pub async fn quality_levels(&self) -> Vec<QualityLevel> {
+        let receivers = self.receiver_sockets.read().await;
+        let mut levels: Vec<QualityLevel> = receivers.iter().map(|r| r.quality.level()).collect();
+        levels.sort();
+        levels.dedup();
+        // In multicast c'è un solo stream: si adatta al receiver messo peggio
+        if self.is_broadcast() {
+            levels.drain(..levels.len().saturating_sub(1));
+        }
+        levels
+    }
 
-            // Usa una read-lock per accedere ai destinatari
+    fn is_broadcast(&self) -> bool {
+        self.transport
+            .as_ref()
+            .as_ref()
+            .is_some_and(|transport| transport.is_broadcast())
+    }
+
+    /// Accoda il frame per i soli receiver serviti al livello di qualità `level`.
+    /// Non attende l'invio: ogni receiver ha la sua coda, svuotata in parallelo.
+    pub async fn send_to_receivers(&self, level: QualityLevel, frame: EncodedFrame) {
+        let Some(transport) = self.transport.as_ref() else {
+            eprintln!("Socket non inizializzato o distrutto.");
+            return;
+        };
+        // Le code vengono copiate: compressione e cifratura avvengono senza tenere il lock
+        let queues: Vec<(Compression, Arc<SendQueue>)> = {
             let receivers = self.receiver_sockets.read().await;
+            let queues = receivers
+                .iter()
+                .filter(|r| r.quality.level() == level)
+                .map(|r| (r.compression, r.queue.clone()));
+            // Un solo invio al gruppo raggiunge tutti i receiver
+            if transport.is_broadcast() {
+                queues.take(1).collect()
+            } else {
+                queues.collect()
+            }
+        };
+        if queues.is_empty() {
+            return;
+        }
 
-            for address in &*receivers {
-                for i in 0..total_packets {
-                    let start = i * (MAX_PAYLOAD - 8);
-                    let end = ((i + 1) * (MAX_PAYLOAD - 8)).min(serialized.len());
-                    let chunk = &serialized[start..end];
-
-                    let mut packet = Vec::with_capacity(MAX_PAYLOAD);
-                    packet.extend(&(i as u32).to_be_bytes()); // Numero del pacchetto
-                    packet.extend(&(total_packets as u32).to_be_bytes()); // Numero totale di pacchetti
-                    packet.extend(chunk); // Dati del pacchetto
-
-                    if let Err(e) = socket.send_to(&packet, address).await {
-                        eprintln!(
-                            "Errore durante l'invio del pacchetto {} a {}: {}",
-                            i, address, e
-                        );
+        let frame_id = self.next_frame_id.fetch_add(1, Ordering::Relaxed);
+        let fps = self.fps.load(Ordering::Relaxed);
+        let timestamp_ms = self.now_ms();
+        let keyframe = frame.is_keyframe();
+        let redundancy = *self.redundancy.read().unwrap();
+        let compressions: HashSet<Compression> = queues.iter().map(|(compression, _)| *compression).collect();
+        let sealer = self.sealer.clone();
+        let transport = self.transport.clone();
+        // Il frame viene compresso una sola volta per ogni algoritmo negoziato
+        let prepared = tokio::task::spawn_blocking(move || {
+            let transport = transport.as_ref().as_ref()?;
+            let mut prepared: HashMap<Compression, QueuedFrame> = HashMap::new();
+            for compression in compressions {
+                let header = FrameHeader {
+                    compression,
+                    compression_level: level.compression_level(),
+                    fps,
+                    timestamp_ms,
+                };
+                let sealed = Self::serialize_frame(&frame, &header)
+                    .and_then(|serialized| Ok(sealer.seal(Channel::Frames, &serialized)?));
+                match sealed {
+                    Ok(sealed) => {
+                        prepared.insert(compression, Arc::new(transport.packetize(frame_id, &sealed, redundancy)));
                     }
+                    Err(e) => eprintln!("Errore durante la preparazione del frame: {}", e),
                 }
             }
-        } else {
-            eprintln!("Socket non inizializzato o distrutto.");
+            Some(prepared)
+        })
+        .await;
+        let prepared = match prepared {
+            Ok(Some(prepared)) => prepared,
+            Ok(None) => return,
+            Err(e) => {
+                eprintln!("Errore durante la preparazione del frame: {}", e);
+                return;
+            }
+        };
+
+        for (compression, queue) in queues {
+            // Un receiver rimasto indietro aspetta un keyframe
+            if let Some(packets) = prepared.get(&compression) {
+                if queue.push(packets.clone(), keyframe) {
+                    self.keyframe_requested.store(true, Ordering::Relaxed);
+                }
+            }
+        }
+    }
+
+    // Comprime e serializza il frame insieme ai suoi metadati
+    fn serialize_frame(
+        frame: &EncodedFrame,
+        header: &FrameHeader,
+    ) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
+        let compression = header.compression;
+        let serializable_image = SerializableImage {
+            width: frame.width(),
+            height: frame.height(),
+            compression,
+            fps: header.fps,
+            timestamp_ms: header.timestamp_ms,
+            data: compression.compress(&bincode::serialize(frame)?, header.compression_level)?,
+        };
+
+        Ok(bincode::serialize(&serializable_image)?)
+    }
+
+    // Invia un messaggio di controllo a un singolo receiver
+    async fn send_to_receiver(&self, address: &str, message: &CasterMessage) {
+        let Some(transport) = self.transport.as_ref() else {
+            return;
+        };
+        let serialized = self
+            .envelope(message)
+            .and_then(|envelope| Ok(bincode::serialize(&envelope)?));
+        let result = match serialized {
+            Ok(serialized) => transport.send_control(address, &serialized).await,
+            Err(e) => Err(std::io::Error::new(std::io::ErrorKind::InvalidData, e)),
+        };
+        if let Err(e) = result {
+            eprintln!("Errore durante l'invio di {:?} a {}: {}", message, address, e);
+        }
+    }
+
+    // Cifra i messaggi per i receiver registrati; le risposte alla registrazione restano in chiaro
+    fn envelope(
+        &self,
+        message: &CasterMessage,
+    ) -> Result<ControlEnvelope, Box<dyn std::error::Error + Send + Sync>> {
+        if message.status().is_none() {
+            return Ok(ControlEnvelope::Plain(message.clone()));
+        }
+        let sealed = self.sealer.seal(Channel::Control, &bincode::serialize(message)?)?;
+        Ok(ControlEnvelope::Sealed(sealed))
+    }
+
+    // Invia un messaggio di controllo a tutti i receiver registrati
+    async fn broadcast(&self, message: &CasterMessage) {
+        let receivers = self.receiver_sockets.read().await;
+        for receiver in receivers.iter() {
+            self.send_to_receiver(&receiver.address, message).await;
+        }
+    }
+
+    // Aggiunge il receiver ai destinatari dello stream e gli invia parametri e chiave di sessione
+    async fn accept_receiver(&self, request: JoinRequest) {
+        let address = request.address.as_str();
+        let grant = match self.sealer.grant(request.public_key, self.access.passphrase.as_deref()) {
+            Ok(grant) => grant,
+            Err(e) => {
+                self.send_to_receiver(address, &CasterMessage::Rejected(e.to_string())).await;
+                return;
+            }
+        };
+        let mut receivers = self.receiver_sockets.write().await;
+        // Una nuova registrazione dallo stesso indirizzo sostituisce la precedente
+        receivers.retain(|receiver| receiver.address != address);
+        // L'indirizzo da cui arriva il messaggio è quello su cui il receiver riceve
+        receivers.push(RegisteredReceiver {
+            address: request.address.clone(),
+            display_name: request.display_name.clone(),
+            compression: request.compression,
+            quality: ReceiverQuality::default(),
+            joined_at: Instant::now(),
+            last_seen: Instant::now(),
+            queue: Arc::new(SendQueue::start(
+                request.address.clone(),
+                self.transport.clone(),
+                self.pacer.clone(),
+                self.termination_rx.clone(),
+            )),
+        });
+        self.notify_viewers(&receivers);
+        drop(receivers);
+        self.keyframe_requested.store(true, Ordering::Relaxed);
+        let info = self.stream_info.read().unwrap().clone();
+        self.send_to_receiver(address, &CasterMessage::Accepted(info, grant)).await;
+        // Chi arriva durante una pausa o un oscuramento lo scopre subito
+        let status = *self.status.read().unwrap();
+        if status != StreamStatus::Live {
+            self.send_to_receiver(address, &status.into()).await;
         }
     }
 
+    // Pubblica l'elenco dei receiver attualmente registrati
+    fn notify_viewers(&self, receivers: &[RegisteredReceiver]) {
+        let _ = self
+            .notification_tx
+            .send(receivers.iter().map(ViewerInfo::from).collect());
+    }
+
+    // Senza PIN o passphrase qualunque richiesta è valida
+    fn passphrase_matches(&self, message: &RegistrationMessage) -> bool {
+        match (&self.access.passphrase, &message.passphrase_proof) {
+            (None, _) => true,
+            (Some(passphrase), Some(proof)) => proof.matches(passphrase),
+            (Some(_), None) => false,
+        }
+    }
+
+    // Rimuove i receiver che non si fanno sentire da più di RECEIVER_TIMEOUT
+    async fn evict_stale_receivers(&self) {
+        let mut receivers = self.receiver_sockets.write().await;
+        receivers.retain(|receiver| receiver.last_seen.elapsed() < RECEIVER_TIMEOUT);
+        // Pubblica comunque l'elenco: riflette anche lo stato delle code di invio
+        self.notify_viewers(&receivers);
+        // Il receiver ha smesso di attendere: la richiesta non è più approvabile
+        self.join_requests
+            .write()
+            .unwrap()
+            .retain(|request| request.requested_at.elapsed() < APPROVAL_TIMEOUT);
+    }
+
     pub async fn listen_for_registration_unregistration(
         &self,
         termination_rx: &mut watch::Receiver<bool>,
     ) {
-        let mut buf = vec![0; 1024];
+        let mut eviction = tokio::time::interval(HEARTBEAT_INTERVAL);
         loop {
             tokio::select! {
                 result = async {
-                    if let Some(socket) = self.socket.as_ref() {
-                        socket.recv_from(&mut buf).await
+                    if let Some(transport) = self.transport.as_ref() {
+                        transport.next_event().await
                     } else {
                         Err(std::io::Error::new(std::io::ErrorKind::Other, "Socket non disponibile"))
                     }
                 } => {
                     match result {
-                        Ok((len, src)) => {
-                            if let Ok(message) = bincode::deserialize::<RegistrationMessage>(&buf[..len]) {
+                        Ok(TransportEvent::Closed(src)) => {
+                            // Connessione chiusa senza disconnessione esplicita
+                            let mut receivers = self.receiver_sockets.write().await;
+                            receivers.retain(|receiver| receiver.address != src);
+                            self.notify_viewers(&receivers);
+                        }
+                        Ok(TransportEvent::Message { data, from: src }) => {
+                            if let Ok(message) = bincode::deserialize::<RegistrationMessage>(&data) {
                                 match message.action {
+                                    Action::Register if self.is_banned(&src) => {
+                                        let reason = "You have been banned from this session".to_string();
+                                        self.send_to_receiver(&src, &CasterMessage::Rejected(reason)).await;
+                                    }
+                                    Action::Register if message.version != PROTOCOL_VERSION => {
+                                        let reason = format!(
+                                            "Protocol version {} is not supported (caster uses {})",
+                                            message.version, PROTOCOL_VERSION
+                                        );
+                                        self.send_to_receiver(&src, &CasterMessage::Rejected(reason)).await;
+                                    }
+                                    Action::Register if !self.passphrase_matches(&message) => {
+                                        self.send_to_receiver(&src, &CasterMessage::InvalidPassphrase).await;
+                                    }
+                                    Action::Register if message.public_key.is_none() => {
+                                        let reason = "Missing key for the encrypted stream".to_string();
+                                        self.send_to_receiver(&src, &CasterMessage::Rejected(reason)).await;
+                                    }
                                     Action::Register => {
                                        //println!("Registrato: {}:{}", message.ip, message.port);
-                                        let mut receivers = self.receiver_sockets.write().await;
-                                        receivers.push(format!("{}:{}", message.ip, message.port));
-                                        let viewer_count = receivers.len();
-                                        let _ = self.notification_tx.send(viewer_count);
+                                        let request = JoinRequest {
+                                            address: src.clone(),
+                                            // Un receiver modificato potrebbe inviare nomi arbitrariamente lunghi
+                                            display_name: message.display_name.map(|name| {
+                                                name.chars().take(MAX_DISPLAY_NAME_LEN).collect()
+                                            }),
+                                            compression: Compression::negotiate(
+                                                self.compression,
+                                                &message.compression,
+                                            ),
+                                            public_key: message.public_key.unwrap(),
+                                            requested_at: Instant::now(),
+                                        };
+                                        // Chi è già registrato sta solo ripetendo la richiesta
+                                        let registered = self
+                                            .receiver_sockets
+                                            .read()
+                                            .await
+                                            .iter()
+                                            .any(|receiver| receiver.address == src);
+                                        if self.access.require_approval && !registered {
+                                            {
+                                                let mut requests = self.join_requests.write().unwrap();
+                                                requests.retain(|request| request.address != src);
+                                                requests.push(request);
+                                            }
+                                            self.send_to_receiver(&src, &CasterMessage::AwaitingApproval).await;
+                                        } else {
+                                            self.accept_receiver(request).await;
+                                        }
                                     }
                                     Action::Disconnect => {
                                         //println!("Disconnesso: {}:{}", message.ip, message.port);
                                         let mut receivers = self.receiver_sockets.write().await;
-                                        receivers.retain(|addr| addr != &format!("{}:{}", message.ip, message.port));
-                                        let viewer_count = receivers.len();
-                                        let _ = self.notification_tx.send(viewer_count);
+                                        receivers.retain(|receiver| receiver.address != src);
+                                        self.notify_viewers(&receivers);
+                                    }
+                                    Action::Heartbeat => {
+                                        let mut receivers = self.receiver_sockets.write().await;
+                                        if let Some(receiver) = receivers.iter_mut().find(|r| r.address == src) {
+                                            receiver.last_seen = Instant::now();
+                                        }
+                                    }
+                                    Action::KeyframeRequest => {
+                                        let mut receivers = self.receiver_sockets.write().await;
+                                        if let Some(receiver) = receivers.iter_mut().find(|r| r.address == src) {
+                                            receiver.last_seen = Instant::now();
+                                            self.keyframe_requested.store(true, Ordering::Relaxed);
+                                        }
+                                    }
+                                    Action::Report(report) => {
+                                        let now_ms = self.now_ms();
+                                        let mut receivers = self.receiver_sockets.write().await;
+                                        if let Some(receiver) = receivers.iter_mut().find(|r| r.address == src) {
+                                            receiver.last_seen = Instant::now();
+                                            // Il nuovo livello riparte da un keyframe
+                                            if receiver.quality.on_report(&report, now_ms) {
+                                                self.keyframe_requested.store(true, Ordering::Relaxed);
+                                            }
+                                            // Aggiorna le perdite mostrate nell'elenco dei receiver
+                                            self.notify_viewers(&receivers);
+                                        }
                                     }
                                 }
                             } else {
@@ -155,6 +898,9 @@ impl CasterSocket {
                         }
                     }
                 }
+                _ = eviction.tick() => {
+                    self.evict_stale_receivers().await;
+                }
                 _ = termination_rx.changed() => {
                     if *termination_rx.borrow() {
                         //println!("Ricevuto segnale di terminazione. Esco dal ciclo.");
@@ -166,24 +912,115 @@ impl CasterSocket {
     }
 
 
+    // Annuncia periodicamente la sessione in ascolto su `local` finché la socket non viene distrutta
+    async fn announce_session(&self, local: SocketAddrV4) {
+        let announcer = match Announcer::bind(*local.ip(), DISCOVERY_GROUP).await {
+            Ok(announcer) => announcer,
+            Err(e) => {
+                eprintln!("Impossibile annunciare la sessione: {}", e);
+                return;
+            }
+        };
+        let mut termination_rx = self.termination_rx.clone();
+        let mut interval = tokio::time::interval(ANNOUNCE_INTERVAL);
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {
+                    let Some(transport) = self.transport.as_ref() else {
+                        break;
+                    };
+                    let session_name = self.stream_info.read().unwrap().session_name.clone();
+                    let announcement = Announcement::new(
+                        session_name,
+                        local.port(),
+                        transport.kind(),
+                        self.receiver_sockets.read().await.len() as u32,
+                        self.access.passphrase.is_some(),
+                    );
+                    if let Err(e) = announcer.announce(&announcement).await {
+                        eprintln!("Annuncio della sessione interrotto: {}", e);
+                        break;
+                    }
+                }
+                _ = termination_rx.changed() => {
+                    if *termination_rx.borrow() {
+                        break;
+                    }
+                }
+            }
+        }
+    }
+
     pub fn destroy(&mut self) {
         let _ = self.termination_tx.send(true); // Segnala al task di terminare
-        self.socket = Arc::new(None);
+        self.transport = Arc::new(None);
         //println!("Socket Caster distrutta.");
     }
 }
 
+// Parametri con cui un frame viene impacchettato per un gruppo di receiver
+struct FrameHeader {
+    compression: Compression,
+    compression_level: u32,
+        fps: u32,
+    timestamp_ms: u32,
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 enum Action {
     Register,
     Disconnect,
+    KeyframeRequest, // Il receiver ha perso il frame di riferimento dei delta
+    Report(FeedbackReport), // Statistiche periodiche del receiver
+    Heartbeat, // Il receiver è ancora attivo
 }
 
 #[derive(Serialize, Deserialize)]
 struct RegistrationMessage {
+    version: u8, // Versione del protocollo del receiver
     ip: String,
     port: u16,
     action: Action,
+    compression: Vec<Compression>, // Algoritmi supportati dal receiver
+    passphrase_proof: Option<PassphraseProof>, // Prova di PIN o passphrase inseriti dall'utente, solo alla registrazione
+    display_name: Option<String>, // Nome mostrato al caster nella richiesta di approvazione
+    public_key: Option<[u8; 32]>, // Chiave X25519 per ricevere la chiave di sessione, solo alla registrazione
+}
+
+/// Prova di conoscenza di PIN o passphrase, inviata al caster al posto del testo in chiaro.
+///
+/// Il sale casuale impedisce di confrontare la prova con valori precalcolati, ma chi
+/// intercetta la richiesta può comunque indovinare un PIN corto per tentativi.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+struct PassphraseProof {
+    salt: [u8; 16],
+    digest: [u8; 32],
+}
+
+impl PassphraseProof {
+    fn new(passphrase: &str) -> Self {
+        let mut salt = [0u8; 16];
+        rand::thread_rng().fill_bytes(&mut salt);
+        PassphraseProof {
+            salt,
+            digest: passphrase_digest(passphrase, &salt),
+        }
+    }
+
+    // Il confronto in tempo costante non rivela quanti byte della prova sono corretti
+    fn matches(&self, passphrase: &str) -> bool {
+        self.digest[..]
+            .ct_eq(&passphrase_digest(passphrase, &self.salt)[..])
+            .into()
+    }
+}
+
+fn passphrase_digest(passphrase: &str, salt: &[u8]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(PASSPHRASE_PROOF_INFO);
+    hasher.update(salt);
+    hasher.update(passphrase.as_bytes());
+    hasher.finalize().into()
 }
 
 #[derive(Error, Debug)]
@@ -192,10 +1029,24 @@ pub enum RegistrationError {
     InvalidIp,
     #[error("Port parsing failed")]
     PortParsingError,
+    #[error("Cannot resolve host {0}")]
+    UnresolvedHost(String),
     #[error("Socket is not initialized")]
     SocketNotInitialized,
     #[error("Connection reset by the remote host")]
     ConnectionReset,
+    #[error("Connection refused by the caster")]
+    ConnectionRefused,
+    #[error("The caster did not answer")]
+    Timeout,
+    #[error("The caster did not approve the request in time")]
+    ApprovalTimeout,
+    #[error("Registration rejected: {0}")]
+    Rejected(String),
+    #[error("Unexpected reply from the caster")]
+    UnexpectedReply,
+    #[error("Wrong session PIN or passphrase")]
+    InvalidPassphrase,
     #[error("Host unreachable")]
     NetworkUnreachable,
     #[error("Unknown error: {0}")]
@@ -207,45 +1058,130 @@ pub enum RegistrationError {
 pub struct ReceiverSocket {
     ip_addr_caster: String,
     ip_addr: String,
-    socket: Arc<Option<UdpSocket>>,
+    transport: Arc<Option<ReceiverChannel>>,
+    stats: Arc<ReceiverStats>,
+    last_keyframe_request: Arc<std::sync::Mutex<Option<Instant>>>,
+    feedback: Arc<std::sync::Mutex<FeedbackTracker>>,
+    last_heartbeat: Arc<std::sync::Mutex<Instant>>,
+    status: Arc<std::sync::RwLock<StreamStatus>>, // Ultimo stato annunciato dal caster
+    passphrase: Option<String>, // Inviata con la richiesta di registrazione
+    display_name: Option<String>, // Mostrato al caster se deve approvare la richiesta
+    opener: Arc<std::sync::Mutex<Option<FrameOpener>>>, // Disponibile dopo la registrazione
+    control_opener: Arc<std::sync::Mutex<Option<FrameOpener>>>, // Messaggi di stato cifrati del caster
 }
 
 impl ReceiverSocket {
-    pub async fn new(ip_addr_receiver: &str, ip_addr_caster: &str) -> Self {
-        let socket = UdpSocket::bind(ip_addr_receiver).await.unwrap();
-        ReceiverSocket {
+    /// Con la porta 0 nell'indirizzo del receiver la sceglie il sistema operativo.
+    pub async fn new(
+        ip_addr_receiver: &str,
+        ip_addr_caster: &str,
+        transport: TransportKind,
+    ) -> std::io::Result<Self> {
+        let stats = Arc::new(ReceiverStats::default());
+        let transport = ReceiverChannel::bind(ip_addr_receiver, transport, stats.clone()).await?;
+        // Indirizzo effettivo, con la porta assegnata dal sistema
+        let ip_addr = match transport.local_addr() {
+            Some(address) => address.to_string(),
+            None => ip_addr_receiver.to_string(),
+        };
+        Ok(ReceiverSocket {
             ip_addr_caster: ip_addr_caster.to_string(),
-            ip_addr: ip_addr_receiver.to_string(),
-            socket: Arc::new(Some(socket)),
+            ip_addr,
+            transport: Arc::new(Some(transport)),
+            stats,
+            last_keyframe_request: Arc::new(std::sync::Mutex::new(None)),
+            feedback: Arc::new(std::sync::Mutex::new(FeedbackTracker::new())),
+            last_heartbeat: Arc::new(std::sync::Mutex::new(Instant::now())),
+            status: Arc::new(std::sync::RwLock::new(StreamStatus::Live)),
+            passphrase: None,
+            display_name: None,
+            opener: Arc::new(std::sync::Mutex::new(None)),
+            control_opener: Arc::new(std::sync::Mutex::new(None)),
+        })
+    }
+
+    /// Imposta il PIN o la passphrase richiesti dal caster, se presenti.
+    pub fn set_passphrase(&mut self, passphrase: Option<String>) {
+        self.passphrase = passphrase;
+    }
+
+    /// Imposta il nome con cui il receiver si presenta al caster.
+    pub fn set_display_name(&mut self, display_name: Option<String>) {
+        self.display_name = display_name;
+    }
+
+    /// Imposta per quanto tempo attendere i pacchetti mancanti prima di scartare un frame.
+    /// Non ha effetto sul trasporto TCP, che non frammenta i frame.
+    pub fn set_reassembly_timeout(&self, timeout: Duration) {
+        match self.transport.as_ref() {
+            Some(ReceiverChannel::Udp(udp)) => udp.reassembler().lock().unwrap().set_timeout(timeout),
+            Some(ReceiverChannel::Multicast(multicast)) => {
+                multicast.reassembler().lock().unwrap().set_timeout(timeout)
+            }
+            _ => {}
+        }
+    }
+
+    pub fn stats(&self) -> Arc<ReceiverStats> {
+        self.stats.clone()
+    }
+
+    /// Stato della trasmissione, aggiornato dai messaggi di controllo del caster.
+    pub fn status(&self) -> Arc<std::sync::RwLock<StreamStatus>> {
+        self.status.clone()
+    }
+
+    // Registra lo stato annunciato da un messaggio di controllo del caster
+    fn apply_status(&self, message: &CasterMessage) {
+        if let Some(status) = message.status() {
+            *self.status.write().unwrap() = status;
+        }
+    }
+
+    // Apre un messaggio di controllo del caster: quelli di stato valgono solo se cifrati
+    fn open_control(&self, data: &[u8]) -> Result<CasterMessage, Box<dyn std::error::Error + Send + Sync>> {
+        match bincode::deserialize::<ControlEnvelope>(data)? {
+            ControlEnvelope::Plain(message) if message.status().is_none() => Ok(message),
+            ControlEnvelope::Plain(_) => Err("Messaggio di stato non cifrato".into()),
+            ControlEnvelope::Sealed(sealed) => {
+                let opened = match self.control_opener.lock().unwrap().as_mut() {
+                    Some(opener) => opener.open(&sealed)?,
+                    None => return Err("Chiave di sessione non ancora ricevuta".into()),
+                };
+                Ok(bincode::deserialize(&opened)?)
+            }
         }
     }
 
     pub async fn receive_from(
         &self,
     ) -> Result<SerializableImage, Box<dyn std::error::Error + Send + Sync>> {
-        if let Some(socket) = self.socket.as_ref() {
-            let mut buf = vec![0u8; MAX_PAYLOAD];
-            let mut received_packets = HashMap::new();
-            let mut total_packets = None;
-
-            while total_packets.map_or(true, |total| received_packets.len() < total as usize) {
-                let received_bytes = socket.recv(&mut buf).await?;
-                let packet_num = u32::from_be_bytes(buf[0..4].try_into()?);
-                let total = u32::from_be_bytes(buf[4..8].try_into()?);
-
-                if total_packets.is_none() {
-                    total_packets = Some(total);
+        if let Some(transport) = self.transport.as_ref() {
+            let frame_data = loop {
+                match transport.recv().await? {
+                    Incoming::Frame(data) => break data,
+                    Incoming::Control(data) => {
+                        match self.open_control(&data) {
+                            // Un'accettazione qui è la risposta a una registrazione ripetuta
+                            Ok(CasterMessage::Accepted(..)) => {}
+                            Ok(message) => self.apply_status(&message),
+                            Err(e) => eprintln!("Messaggio di controllo non valido: {}", e),
+                        }
+                    }
                 }
+            };
 
-                received_packets.insert(packet_num, buf[8..received_bytes].to_vec());
-            }
-
-            let mut compressed_data = Vec::new();
-            for i in 0..total_packets.unwrap() {
-                compressed_data.extend(received_packets.remove(&i).unwrap());
-            }
-
-            let deserialized_image: SerializableImage = bincode::deserialize(&compressed_data)?;
+            // Frame alterati o ripetuti vengono scartati qui
+            let frame_data = match self.opener.lock().unwrap().as_mut() {
+                Some(opener) => opener.open(&frame_data)?,
+                None => return Err("Chiave di sessione non ancora ricevuta".into()),
+            };
+            let deserialized_image: SerializableImage = bincode::deserialize(&frame_data)?;
+            self.stats().set_caster_fps(deserialized_image.fps());
+            self.feedback
+                .lock()
+                .unwrap()
+                .frame_received(deserialized_image.timestamp_ms);
 
             Ok(deserialized_image)
         } else {
@@ -253,37 +1189,31 @@ impl ReceiverSocket {
         }
     }
 
+    /// Invia la richiesta di registrazione e attende per `REGISTRATION_TIMEOUT`
+    /// la risposta del caster con i parametri dello stream.
     pub async fn register_with_caster(
         &self,
-    ) -> Result<(), RegistrationError> {
-        // Controlla se l'indirizzo IP del caster è valido
-        let ip_parts: Vec<&str> = self.ip_addr_caster.split(':').collect();
-        if ip_parts.len() != 2 {
+    ) -> Result<StreamInfo, RegistrationError> {
+        // Controlla se l'indirizzo del caster è valido (già risolto, vedi `resolve_caster_address`)
+        if self.ip_addr_caster.parse::<SocketAddr>().is_err() {
             return Err(RegistrationError::InvalidIp);
         }
-    
-        // Valida l'IP
-        let ip = ip_parts[0].parse::<IpAddr>();
-        if ip.is_err() {
-            return Err(RegistrationError::InvalidIp);
-        }
-
-        // Valida la porta
-        let port = ip_parts[1].parse::<u16>();
-        if port.is_err() {
-            return Err(RegistrationError::PortParsingError);
-        }
-
-        let ip_parts_receiver: Vec<&str> = self.ip_addr.split(':').collect();
-        let ip_receiver = ip_parts_receiver[0].parse::<IpAddr>().unwrap();
-        let port_receiver = ip_parts_receiver[1].parse::<u16>().unwrap();
-        //println!("Receiver: {} {}", ip_receiver, port_receiver);
+        let receiver = self.local_addr();
+        //println!("Receiver: {}", receiver);
         
+        // Ogni richiesta usa una nuova chiave: le risposte a richieste precedenti non si decifrano
+        let key_exchange = KeyExchange::new();
+
         // Crea il messaggio di registrazione
         let message = RegistrationMessage {
-            ip: ip_receiver.to_string(),
-            port: port_receiver,
+            version: PROTOCOL_VERSION,
+            ip: receiver.ip().to_string(),
+            port: receiver.port(),
             action: Action::Register,
+            compression: Compression::SUPPORTED.to_vec(),
+            passphrase_proof: self.passphrase.as_deref().map(PassphraseProof::new),
+            display_name: self.display_name.clone(),
+            public_key: Some(key_exchange.public_key()),
         };
     
         let serialized = match bincode::serialize(&message) {
@@ -292,45 +1222,311 @@ impl ReceiverSocket {
         };
     
         // Controlla se la socket è disponibile
-        if let Some(socket) = self.socket.as_ref() {
-            // Invia il messaggio di registrazione
-            match socket.send_to(&serialized, &self.ip_addr_caster).await {
-                Ok(_) => Ok(()),
-                Err(e) => {
-                    match e.kind() {
-                        tokio::io::ErrorKind::ConnectionReset => Err(RegistrationError::ConnectionReset),
-                        tokio::io::ErrorKind::AddrNotAvailable => Err(RegistrationError::NetworkUnreachable),
-                        _ => Err(RegistrationError::UnknownError(e.to_string())),
+        if let Some(transport) = self.transport.as_ref() {
+            // Apre la connessione (solo TCP) e invia il messaggio di registrazione
+            transport
+                .connect(&self.ip_addr_caster)
+                .await
+                .map_err(registration_error)?;
+            transport
+                .send_control(&self.ip_addr_caster, &serialized)
+                .await
+                .map_err(registration_error)?;
+
+            // Se la richiesta va approvata il caster lo segnala subito: si attende la sua decisione
+            let mut wait = REGISTRATION_TIMEOUT;
+            loop {
+                return match tokio::time::timeout(wait, self.next_reply(transport)).await {
+                    Ok(Ok(CasterMessage::AwaitingApproval)) => {
+                        wait = APPROVAL_TIMEOUT;
+                        continue;
                     }
-                }
+                    Ok(Ok(CasterMessage::Accepted(info, grant))) => {
+                        match key_exchange.accept(&grant, self.passphrase.as_deref()) {
+                            Ok(key) => {
+                                *self.opener.lock().unwrap() = Some(FrameOpener::new(key, Channel::Frames));
+                                *self.control_opener.lock().unwrap() = Some(FrameOpener::new(key, Channel::Control));
+                                // Solo ora il receiver entra nel gruppo: prima non riceve frame
+                                if let Some(group) = info.multicast_group {
+                                    transport.join_group(group).map_err(registration_error)?;
+                                }
+                                Ok(info)
+                            }
+                            // Risposta a un tentativo precedente: si attende quella a questo
+                            Err(_) => continue,
+                        }
+                    }
+                    Ok(Ok(CasterMessage::Rejected(reason))) => Err(RegistrationError::Rejected(reason)),
+                    Ok(Ok(CasterMessage::InvalidPassphrase)) => Err(RegistrationError::InvalidPassphrase),
+                    // L'attesa termina solo con accettazioni e rifiuti, qualunque altra risposta è un errore
+                    Ok(Ok(_)) => Err(RegistrationError::UnexpectedReply),
+                    Ok(Err(e)) => Err(boxed_registration_error(e)),
+                    Err(_) if wait == APPROVAL_TIMEOUT => Err(RegistrationError::ApprovalTimeout),
+                    Err(_) => Err(RegistrationError::Timeout),
+                };
             }
         } else {
             Err(RegistrationError::SocketNotInitialized)
         }
     }
 
+    // Attende la prossima risposta alla registrazione: i messaggi di stato vengono
+    // registrati, i frame arrivati prima della risposta ignorati
+    async fn next_reply(
+        &self,
+        transport: &ReceiverChannel,
+    ) -> Result<CasterMessage, Box<dyn std::error::Error + Send + Sync>> {
+        loop {
+            if let Incoming::Control(data) = transport.recv().await? {
+                if let Ok(message) = self.open_control(&data) {
+                    if message.status().is_none() {
+                        return Ok(message);
+                    }
+                    self.apply_status(&message);
+                }
+            }
+        }
+    }
+
     // Invia un messaggio di disconnessione al caster
     pub async fn unregister_with_caster(
         &self,
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.send_action(Action::Disconnect).await
+    }
+
+    /// Chiede al caster un keyframe, al più una volta per KEYFRAME_REQUEST_INTERVAL:
+    /// i delta successivi a un frame perso non sono decodificabili fino al prossimo.
+    pub async fn request_keyframe(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        {
+            let mut last_request = self.last_keyframe_request.lock().unwrap();
+            if last_request.is_some_and(|last| last.elapsed() < KEYFRAME_REQUEST_INTERVAL) {
+                return Ok(());
+            }
+            *last_request = Some(Instant::now());
+        }
+        self.send_action(Action::KeyframeRequest).await
+    }
+
+    /// Invia al caster il report sulla qualità della ricezione, se è il momento.
+    pub async fn send_feedback(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let stats = self.stats();
+        let Some(report) = self.feedback.lock().unwrap().report_if_due(&stats) else {
+            return Ok(());
+        };
+        self.send_action(Action::Report(report)).await
+    }
+
+    /// Segnala al caster che il receiver è ancora attivo, al più una volta per HEARTBEAT_INTERVAL.
+    pub async fn send_heartbeat(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        {
+            let mut last_heartbeat = self.last_heartbeat.lock().unwrap();
+            if last_heartbeat.elapsed() < HEARTBEAT_INTERVAL {
+                return Ok(());
+            }
+            *last_heartbeat = Instant::now();
+        }
+        self.send_action(Action::Heartbeat).await
+    }
+
+    async fn send_action(&self, action: Action) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let receiver = self.local_addr();
         let message = RegistrationMessage {
-            ip: self.ip_addr.split(':').next().unwrap().to_string(),
-            port: self.ip_addr.split(':').nth(1).unwrap().parse().unwrap(),
-            action: Action::Disconnect,
+            version: PROTOCOL_VERSION,
+            ip: receiver.ip().to_string(),
+            port: receiver.port(),
+            action,
+            compression: Vec::new(),
+            passphrase_proof: None,
+            display_name: None,
+            public_key: None,
         };
 
         let serialized = bincode::serialize(&message)?;
 
-        if let Some(socket) = self.socket.as_ref() {
-            socket.send_to(&serialized, &self.ip_addr_caster).await?;
+        if let Some(transport) = self.transport.as_ref() {
+            transport.send_control(&self.ip_addr_caster, &serialized).await?;
             Ok(())
         } else {
             Err("Socket non inizializzata".into())
         }
     }
 
+    // Indirizzo del receiver comunicato al caster; senza una socket propria (TCP) è quello di bind
+    fn local_addr(&self) -> SocketAddr {
+        self.ip_addr
+            .parse()
+            .unwrap_or(SocketAddr::from((Ipv4Addr::UNSPECIFIED, 0)))
+    }
+
     pub fn destroy(&mut self) {
-        self.socket = Arc::new(None);
+        self.transport = Arc::new(None);
         //println!("Socket Receiver distrutta.");
     }
 }
+
+/// Interpreta l'indirizzo del caster inserito dall'utente: IPv4, IPv6 (tra parentesi quadre
+/// se seguito dalla porta) o nome host, con la porta facoltativa.
+pub async fn resolve_caster_address(input: &str) -> Result<SocketAddr, RegistrationError> {
+    let input = input.trim();
+    if let Ok(address) = input.parse::<SocketAddr>() {
+        return Ok(address);
+    }
+    // Indirizzo senza porta, anche IPv6 tra parentesi quadre
+    let bare = input.strip_prefix('[').and_then(|ip| ip.strip_suffix(']')).unwrap_or(input);
+    if let Ok(ip) = bare.parse::<IpAddr>() {
+        return Ok(SocketAddr::new(ip, DEFAULT_PORT));
+    }
+
+    // Resta un nome host con la porta facoltativa: parentesi quadre o più ':' indicano
+    // un indirizzo IP malformato
+    if input.contains(['[', ']']) || input.matches(':').count() > 1 {
+        return Err(RegistrationError::InvalidIp);
+    }
+    let (host, port) = match input.split_once(':') {
+        Some((host, port)) => (host, port.parse::<u16>().map_err(|_| RegistrationError::PortParsingError)?),
+        None => (input, DEFAULT_PORT),
+    };
+    if host.is_empty() {
+        return Err(RegistrationError::InvalidIp);
+    }
+    tokio::net::lookup_host((host, port))
+        .await
+        .ok()
+        .and_then(|mut addresses| addresses.next())
+        .ok_or_else(|| RegistrationError::UnresolvedHost(host.to_string()))
+}
+
+/// Indirizzo su cui aprire la socket del receiver per raggiungere `caster`, con la porta
+/// scelta dal sistema operativo.
+pub fn receiver_bind_address(caster: SocketAddr) -> SocketAddr {
+    let ip = match caster.ip() {
+        ip if ip.is_loopback() => ip,
+        // L'interfaccia principale è anche quella su cui si riceve il multicast
+        IpAddr::V4(_) => local_ip_address::local_ip().unwrap_or(IpAddr::V4(Ipv4Addr::UNSPECIFIED)),
+        IpAddr::V6(_) => IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+    };
+    SocketAddr::new(ip, 0)
+}
+
+fn boxed_registration_error(e: Box<dyn std::error::Error + Send + Sync>) -> RegistrationError {
+    match e.downcast::<std::io::Error>() {
+        Ok(e) => registration_error(*e),
+        Err(e) => RegistrationError::UnknownError(e.to_string()),
+    }
+}
+
+fn registration_error(e: std::io::Error) -> RegistrationError {
+    match e.kind() {
+        std::io::ErrorKind::ConnectionReset => RegistrationError::ConnectionReset,
+        std::io::ErrorKind::ConnectionRefused => RegistrationError::ConnectionRefused,
+        std::io::ErrorKind::AddrNotAvailable => RegistrationError::NetworkUnreachable,
+        _ => RegistrationError::UnknownError(e.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encoded(header: &PacketHeader, payload: &[u8]) -> Vec<u8> {
+        let mut packet = Vec::new();
+        header.encode(&mut packet);
+        packet.extend(payload);
+        packet
+    }
+
+    #[test]
+    fn header_roundtrip() {
+        let payload = b"chunk di prova";
+        let header = PacketHeader::new(PayloadKind::Frame, 42, 3, 7, payload);
+        let packet = encoded(&header, payload);
+        assert_eq!(packet.len(), HEADER_SIZE + payload.len());
+        let (decoded, decoded_payload) = PacketHeader::decode(&packet).unwrap();
+        assert_eq!(decoded, header);
+        assert_eq!(decoded_payload, payload);
+    }
+
+    #[test]
+    fn foreign_and_mismatched_packets_are_rejected() {
+        let payload = b"dati";
+        let packet = encoded(&PacketHeader::new(PayloadKind::Frame, 1, 0, 1, payload), payload);
+
+        let mut foreign = packet.clone();
+        foreign[0] ^= 0xff;
+        assert!(matches!(PacketHeader::decode(&foreign), Err(ProtocolError::InvalidMagic(_))));
+
+        let mut newer = packet.clone();
+        newer[4] = PROTOCOL_VERSION + 1;
+        assert!(matches!(
+            PacketHeader::decode(&newer),
+            Err(ProtocolError::UnsupportedVersion { found, .. }) if found == PROTOCOL_VERSION + 1
+        ));
+
+        let mut corrupted = packet.clone();
+        *corrupted.last_mut().unwrap() ^= 1;
+        assert!(matches!(PacketHeader::decode(&corrupted), Err(ProtocolError::ChecksumMismatch { .. })));
+
+        assert!(matches!(PacketHeader::decode(&packet[..HEADER_SIZE - 1]), Err(ProtocolError::Truncated(_))));
+        assert!(matches!(PacketHeader::decode(&packet[..packet.len() - 1]), Err(ProtocolError::Truncated(_))));
+    }
+
+    #[test]
+    fn chunk_index_must_be_within_count() {
+        let payload = b"dati";
+        for (index, count) in [(2, 2), (0, 0)] {
+            let packet = encoded(&PacketHeader::new(PayloadKind::Frame, 1, index, count, payload), payload);
+            assert!(matches!(PacketHeader::decode(&packet), Err(ProtocolError::InvalidChunk { .. })));
+        }
+    }
+
+    #[test]
+    fn passphrase_proof_hides_and_checks_the_passphrase() {
+        let proof = PassphraseProof::new("482913");
+        assert!(proof.matches("482913"));
+        assert!(!proof.matches("482914"));
+        assert!(!proof.matches(""));
+
+        // Sali diversi producono prove diverse per la stessa passphrase
+        let other = PassphraseProof::new("482913");
+        assert_ne!(proof.salt, other.salt);
+        assert_ne!(proof.digest, other.digest);
+    }
+
+    #[tokio::test]
+    async fn caster_addresses_accept_ips_hosts_and_optional_ports() {
+        let cases = [
+            ("192.168.1.20:9000", "192.168.1.20", 9000),
+            (" 192.168.1.20 ", "192.168.1.20", DEFAULT_PORT),
+            ("[fe80::1]:9000", "fe80::1", 9000),
+            ("[fe80::1]", "fe80::1", DEFAULT_PORT),
+            ("fe80::1", "fe80::1", DEFAULT_PORT),
+        ];
+        for (input, ip, port) in cases {
+            let expected = SocketAddr::new(ip.parse().unwrap(), port);
+            assert_eq!(resolve_caster_address(input).await.unwrap(), expected);
+        }
+
+        let host = resolve_caster_address("localhost:9000").await.unwrap();
+        assert!(host.ip().is_loopback());
+        assert_eq!(host.port(), 9000);
+    }
+
+    #[tokio::test]
+    async fn malformed_caster_addresses_are_rejected() {
+        for input in ["", ":9000", "[fe80::1", "fe80::1]:9000", "fe80::zz"] {
+            assert!(
+                matches!(resolve_caster_address(input).await, Err(RegistrationError::InvalidIp)),
+                "{:?}",
+                input
+            );
+        }
+        for input in ["192.168.1.20:", "192.168.1.20:porta", "localhost:70000"] {
+            assert!(
+                matches!(resolve_caster_address(input).await, Err(RegistrationError::PortParsingError)),
+                "{:?}",
+                input
+            );
+        }
+    }
+}
@@ -0,0 +1,72 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::socket::reassembly::ReceiverStats;
+use crate::socket::socket::{PacketHeader, PayloadKind};
+use crate::socket::transport::{
+    CasterChannel, CasterTransport, Incoming, ReceiverChannel, ReceiverTransport, TransportEvent,
+    TransportKind,
+};
+
+/// Tempo massimo di attesa di un messaggio nei test su loopback.
+pub const RECV_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Header e payload dei pacchetti che trasportano `chunks` come frame `frame_id`.
+pub fn data_packets(frame_id: u32, chunks: &[Vec<u8>]) -> Vec<(PacketHeader, Vec<u8>)> {
+    chunks
+        .iter()
+        .enumerate()
+        .map(|(i, chunk)| {
+            let header = PacketHeader::new(PayloadKind::Frame, frame_id, i as u32, chunks.len() as u32, chunk);
+            (header, chunk.clone())
+        })
+        .collect()
+}
+
+/// Dati di prova non comprimibili banalmente, lunghi `len` byte.
+pub fn pattern(len: usize) -> Vec<u8> {
+    (0..len as u32).map(|i| (i * 31 % 251) as u8).collect()
+}
+
+/// Caster e receiver collegati su loopback. Restituisce anche l'indirizzo
+/// del receiver visto dal caster, a cui inviare i frame.
+pub async fn loopback(kind: TransportKind) -> (CasterChannel, ReceiverChannel, String, Arc<ReceiverStats>) {
+    let caster = CasterChannel::bind("127.0.0.1:0", kind).await.unwrap();
+    let (receiver, from, stats) = connect_receiver(&caster, kind).await;
+    (caster, receiver, from, stats)
+}
+
+/// Collega un nuovo receiver su loopback a `caster`.
+pub async fn connect_receiver(
+    caster: &CasterChannel,
+    kind: TransportKind,
+) -> (ReceiverChannel, String, Arc<ReceiverStats>) {
+    let caster_addr = caster.local_addr().unwrap().to_string();
+    let stats = Arc::new(ReceiverStats::default());
+    let receiver = ReceiverChannel::bind("127.0.0.1:0", kind, stats.clone()).await.unwrap();
+    receiver.connect(&caster_addr).await.unwrap();
+    receiver.send_control(&caster_addr, b"hello").await.unwrap();
+
+    let event = tokio::time::timeout(RECV_TIMEOUT, caster.next_event()).await.unwrap().unwrap();
+    let TransportEvent::Message { data, from } = event else {
+        panic!("atteso un messaggio, ricevuto {:?}", event);
+    };
+    assert_eq!(data, b"hello");
+    // Come all'accettazione della registrazione
+    if let Some(group) = caster.multicast_group() {
+        receiver.join_group(group).unwrap();
+    }
+    (receiver, from, stats)
+}
+
+/// Attende il prossimo frame, facendo fallire il test se non arriva.
+pub async fn recv_frame(receiver: &ReceiverChannel) -> Vec<u8> {
+    let incoming = tokio::time::timeout(RECV_TIMEOUT, receiver.recv())
+        .await
+        .expect("nessun frame ricevuto")
+        .unwrap();
+    match incoming {
+        Incoming::Frame(data) => data,
+        other => panic!("atteso un frame, ricevuto {:?}", other),
+    }
+}
@@ -0,0 +1,849 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, SocketAddrV4};
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use socket2::{Domain, Protocol, SockRef, Socket, Type};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::net::{TcpListener, TcpStream, UdpSocket};
+use tokio::sync::{mpsc, Mutex, RwLock};
+
+use crate::socket::discovery::DISCOVERY_GROUP;
+use crate::socket::fec::{self, Redundancy, FEC_CHUNK_PAYLOAD};
+use crate::socket::reassembly::{FrameReassembler, ReceiverStats, DEFAULT_REASSEMBLY_TIMEOUT};
+use crate::socket::socket::{PacketHeader, PayloadKind, HEADER_SIZE, MAX_CHUNK_PAYLOAD, MAX_PAYLOAD};
+
+/// Dimensione massima di un messaggio TCP, per non allocare su un prefisso corrotto.
+const MAX_TCP_MESSAGE: u32 = 64 * 1024 * 1024;
+/// Dimensione del buffer per i messaggi di controllo ricevuti via UDP: occupano un solo datagramma.
+const CONTROL_BUFFER_SIZE: usize = MAX_PAYLOAD;
+/// Buffer di invio e ricezione delle socket UDP: diversi frame in volo anche ad alta banda.
+const SOCKET_BUFFER_SIZE: usize = 4 * 1024 * 1024;
+/// Tempo massimo per scrivere un frame su una connessione TCP: oltre, il receiver è troppo lento.
+pub const TCP_WRITE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Gruppo multicast su cui un caster in ascolto su `caster` invia i frame.
+///
+/// L'indirizzo (in 239.255.0.0/16) dipende da IP e porta del caster, così più caster
+/// sulla stessa rete usano gruppi diversi; la porta è quella successiva alla sua.
+/// Gruppo e porta della discovery non vengono mai usati per i frame.
+pub fn multicast_group(caster: SocketAddr) -> SocketAddrV4 {
+    let mut crc = flate2::Crc::new();
+    match caster.ip() {
+        IpAddr::V4(ip) => crc.update(&ip.octets()),
+        IpAddr::V6(ip) => crc.update(&ip.octets()),
+    }
+    crc.update(&caster.port().to_be_bytes());
+    let [_, _, high, low] = crc.sum().to_be_bytes();
+    let mut ip = Ipv4Addr::new(239, 255, high, low);
+    if ip == *DISCOVERY_GROUP.ip() {
+        ip = Ipv4Addr::new(239, 255, high, low ^ 1);
+    }
+    let mut port = caster.port().checked_add(1).unwrap_or(caster.port() - 1);
+    if port == DISCOVERY_GROUP.port() {
+        port += 1;
+    }
+    SocketAddrV4::new(ip, port)
+}
+
+/// Trasporto usato tra caster e receiver.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TransportKind {
+    /// Datagrammi con header e frammentazione propria, tollera le perdite
+    #[default]
+    Udp,
+    /// Connessione affidabile con messaggi preceduti dalla lunghezza
+    Tcp,
+    /// Frame inviati una sola volta a un gruppo multicast, controllo in unicast UDP
+    Multicast,
+}
+
+impl TransportKind {
+    pub const ALL: [TransportKind; 3] = [
+        TransportKind::Udp,
+        TransportKind::Tcp,
+        TransportKind::Multicast,
+    ];
+}
+
+impl fmt::Display for TransportKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TransportKind::Udp => write!(f, "UDP"),
+            TransportKind::Tcp => write!(f, "TCP"),
+            TransportKind::Multicast => write!(f, "Multicast"),
+        }
+    }
+}
+
+/// Evento ricevuto dal caster su un trasporto.
+#[derive(Debug)]
+pub enum TransportEvent {
+    /// Messaggio di controllo (registrazione, report, ...) e indirizzo del mittente
+    Message { data: Vec<u8>, from: String },
+    /// Il receiver ha chiuso la connessione (solo trasporti connessi)
+    Closed(String),
+}
+
+/// Messaggio ricevuto dal receiver: un frame completo o un messaggio di controllo del caster.
+#[derive(Debug)]
+pub enum Incoming {
+    Frame(Vec<u8>),
+    Control(Vec<u8>),
+}
+
+/// Lato caster del trasporto: invia i frame e riceve i messaggi di controllo.
+pub trait CasterTransport {
+    /// Divide un frame serializzato nei messaggi da inviare sul trasporto,
+    /// aggiungendo i chunk di parità richiesti da `redundancy` se il trasporto può perderne.
+    fn packetize(&self, frame_id: u32, data: &[u8], redundancy: Redundancy) -> Vec<Vec<u8>>;
+
+    fn send_packets(
+        &self,
+        address: &str,
+        packets: &[Vec<u8>],
+    ) -> impl Future<Output = io::Result<()>> + Send;
+
+    /// Invia un messaggio di controllo a un solo receiver, anche in multicast.
+    fn send_control(&self, address: &str, data: &[u8]) -> impl Future<Output = io::Result<()>> + Send;
+
+    /// True se un solo invio raggiunge tutti i receiver (l'indirizzo viene ignorato).
+    fn is_broadcast(&self) -> bool {
+        false
+    }
+
+    fn next_event(&self) -> impl Future<Output = io::Result<TransportEvent>> + Send;
+}
+
+/// Lato receiver del trasporto: invia i messaggi di controllo e riceve i frame.
+pub trait ReceiverTransport {
+    /// Apre la connessione verso il caster, se il trasporto ne richiede una.
+    fn connect(&self, caster: &str) -> impl Future<Output = io::Result<()>> + Send;
+
+    fn send_control(&self, caster: &str, data: &[u8]) -> impl Future<Output = io::Result<()>> + Send;
+
+    /// Attende il prossimo frame completo o messaggio di controllo.
+    fn recv(&self) -> impl Future<Output = Result<Incoming, Box<dyn std::error::Error + Send + Sync>>> + Send;
+}
+
+/// Trasporto del caster scelto all'avvio dello streaming.
+#[derive(Debug)]
+pub enum CasterChannel {
+    Udp(UdpCaster),
+    Tcp(TcpCaster),
+    Multicast(MulticastCaster),
+}
+
+impl CasterChannel {
+    pub async fn bind(address: &str, kind: TransportKind) -> io::Result<Self> {
+        Ok(match kind {
+            TransportKind::Udp => {
+                let socket = UdpSocket::bind(address).await?;
+                set_buffer_sizes(&socket);
+                CasterChannel::Udp(UdpCaster { socket })
+            }
+            TransportKind::Tcp => CasterChannel::Tcp(TcpCaster::bind(address).await?),
+            TransportKind::Multicast => {
+                CasterChannel::Multicast(MulticastCaster::bind(address).await?)
+            }
+        })
+    }
+
+    pub fn kind(&self) -> TransportKind {
+        match self {
+            CasterChannel::Udp(_) => TransportKind::Udp,
+            CasterChannel::Tcp(_) => TransportKind::Tcp,
+            CasterChannel::Multicast(_) => TransportKind::Multicast,
+        }
+    }
+
+    /// Indirizzo su cui il caster riceve le registrazioni.
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        match self {
+            CasterChannel::Udp(udp) => udp.socket.local_addr(),
+            CasterChannel::Tcp(tcp) => Ok(tcp.local_addr),
+            CasterChannel::Multicast(multicast) => multicast.unicast.socket.local_addr(),
+        }
+    }
+
+    /// Gruppo su cui vengono inviati i frame, comunicato ai receiver all'accettazione.
+    pub fn multicast_group(&self) -> Option<SocketAddrV4> {
+        match self {
+            CasterChannel::Multicast(multicast) => Some(multicast.group),
+            CasterChannel::Udp(_) | CasterChannel::Tcp(_) => None,
+        }
+    }
+}
+
+impl CasterTransport for CasterChannel {
+    fn packetize(&self, frame_id: u32, data: &[u8], redundancy: Redundancy) -> Vec<Vec<u8>> {
+        match self {
+            CasterChannel::Udp(udp) => udp.packetize(frame_id, data, redundancy),
+            CasterChannel::Tcp(tcp) => tcp.packetize(frame_id, data, redundancy),
+            CasterChannel::Multicast(multicast) => multicast.packetize(frame_id, data, redundancy),
+        }
+    }
+
+    async fn send_packets(&self, address: &str, packets: &[Vec<u8>]) -> io::Result<()> {
+        match self {
+            CasterChannel::Udp(udp) => udp.send_packets(address, packets).await,
+            CasterChannel::Tcp(tcp) => tcp.send_packets(address, packets).await,
+            CasterChannel::Multicast(multicast) => multicast.send_packets(address, packets).await,
+        }
+    }
+
+    async fn send_control(&self, address: &str, data: &[u8]) -> io::Result<()> {
+        match self {
+            CasterChannel::Udp(udp) => udp.send_control(address, data).await,
+            CasterChannel::Tcp(tcp) => tcp.send_control(address, data).await,
+            CasterChannel::Multicast(multicast) => multicast.send_control(address, data).await,
+        }
+    }
+
+    fn is_broadcast(&self) -> bool {
+        match self {
+            CasterChannel::Udp(udp) => udp.is_broadcast(),
+            CasterChannel::Tcp(tcp) => tcp.is_broadcast(),
+            CasterChannel::Multicast(multicast) => multicast.is_broadcast(),
+        }
+    }
+
+    async fn next_event(&self) -> io::Result<TransportEvent> {
+        match self {
+            CasterChannel::Udp(udp) => udp.next_event().await,
+            CasterChannel::Tcp(tcp) => tcp.next_event().await,
+            CasterChannel::Multicast(multicast) => multicast.next_event().await,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct UdpCaster {
+    socket: UdpSocket,
+}
+
+impl CasterTransport for UdpCaster {
+    fn packetize(&self, frame_id: u32, data: &[u8], redundancy: Redundancy) -> Vec<Vec<u8>> {
+        // Con la FEC i chunk si accorciano per lasciare spazio ai parametri nei chunk di parità
+        let chunk_size = match redundancy {
+            Redundancy::Off => MAX_CHUNK_PAYLOAD,
+            Redundancy::Percent(_) => FEC_CHUNK_PAYLOAD,
+        };
+        let chunks: Vec<&[u8]> = data.chunks(chunk_size).collect();
+        let total_packets = chunks.len() as u32;
+        let parity = fec::parity_chunks(&chunks, data.len(), redundancy);
+
+        let data_packets = chunks.iter().enumerate().map(|(i, chunk)| (PayloadKind::Frame, i, *chunk));
+        let parity_packets = parity.iter().enumerate().map(|(i, chunk)| (PayloadKind::Parity, i, chunk.as_slice()));
+        data_packets
+            .chain(parity_packets)
+            .map(|(kind, i, chunk)| {
+                let header = PacketHeader::new(kind, frame_id, i as u32, total_packets, chunk);
+                let mut packet = Vec::with_capacity(HEADER_SIZE + chunk.len());
+                header.encode(&mut packet);
+                packet.extend(chunk); // Dati del pacchetto
+                packet
+            })
+            .collect()
+    }
+
+    async fn send_packets(&self, address: &str, packets: &[Vec<u8>]) -> io::Result<()> {
+        for packet in packets {
+            self.socket.send_to(packet, address).await?;
+        }
+        Ok(())
+    }
+
+    async fn send_control(&self, address: &str, data: &[u8]) -> io::Result<()> {
+        // Un messaggio di controllo occupa sempre un solo datagramma
+        let header = PacketHeader::new(PayloadKind::Control, 0, 0, 1, data);
+        let mut packet = Vec::with_capacity(HEADER_SIZE + data.len());
+        header.encode(&mut packet);
+        packet.extend(data);
+        self.socket.send_to(&packet, address).await.map(|_| ())
+    }
+
+    async fn next_event(&self) -> io::Result<TransportEvent> {
+        let mut buf = vec![0; CONTROL_BUFFER_SIZE];
+        let (len, src) = self.socket.recv_from(&mut buf).await?;
+        buf.truncate(len);
+        Ok(TransportEvent::Message {
+            data: buf,
+            from: src.to_string(),
+        })
+    }
+}
+
+/// Caster multicast: registrazioni e report arrivano in unicast sulla socket
+/// del caster, i frame vengono inviati una sola volta al gruppo.
+#[derive(Debug)]
+pub struct MulticastCaster {
+    unicast: UdpCaster,
+    group: SocketAddrV4,
+}
+
+impl MulticastCaster {
+    async fn bind(address: &str) -> io::Result<Self> {
+        let socket = UdpSocket::bind(address).await?;
+        set_buffer_sizes(&socket);
+        let group = multicast_group(socket.local_addr()?);
+        // I datagrammi al gruppo escono dall'interfaccia su cui è in ascolto il caster
+        if let SocketAddr::V4(local) = socket.local_addr()? {
+            if !local.ip().is_unspecified() {
+                SockRef::from(&socket).set_multicast_if_v4(local.ip())?;
+            }
+        }
+        socket.set_multicast_loop_v4(true)?;
+        Ok(MulticastCaster {
+            unicast: UdpCaster { socket },
+            group,
+        })
+    }
+}
+
+impl CasterTransport for MulticastCaster {
+    fn packetize(&self, frame_id: u32, data: &[u8], redundancy: Redundancy) -> Vec<Vec<u8>> {
+        self.unicast.packetize(frame_id, data, redundancy)
+    }
+
+    async fn send_packets(&self, _address: &str, packets: &[Vec<u8>]) -> io::Result<()> {
+        for packet in packets {
+            self.unicast.socket.send_to(packet, self.group).await?;
+        }
+        Ok(())
+    }
+
+    async fn send_control(&self, address: &str, data: &[u8]) -> io::Result<()> {
+        self.unicast.send_control(address, data).await
+    }
+
+    fn is_broadcast(&self) -> bool {
+        true
+    }
+
+    async fn next_event(&self) -> io::Result<TransportEvent> {
+        self.unicast.next_event().await
+    }
+}
+
+type TcpConnections = Arc<RwLock<HashMap<String, Arc<Mutex<OwnedWriteHalf>>>>>;
+
+/// Caster TCP: una connessione per receiver, identificata dall'indirizzo remoto.
+///
+/// Connessioni accettate e chiuse vengono gestite da task dedicati: `next_event`
+/// si limita a leggere dal canale degli eventi, quindi può essere interrotto
+/// (ad esempio da un `select!`) senza perdere connessioni o eventi.
+#[derive(Debug)]
+pub struct TcpCaster {
+    local_addr: SocketAddr,
+    connections: TcpConnections,
+    events_rx: Mutex<mpsc::UnboundedReceiver<TransportEvent>>,
+    accept_task: tokio::task::JoinHandle<()>,
+}
+
+impl TcpCaster {
+    async fn bind(address: &str) -> io::Result<Self> {
+        let listener = TcpListener::bind(address).await?;
+        let local_addr = listener.local_addr()?;
+        let connections = TcpConnections::default();
+        let (events_tx, events_rx) = mpsc::unbounded_channel();
+        let accept_task = tokio::spawn(accept_connections(listener, connections.clone(), events_tx));
+        Ok(TcpCaster {
+            local_addr,
+            connections,
+            events_rx: Mutex::new(events_rx),
+            accept_task,
+        })
+    }
+}
+
+// Il listener vive nel task di accettazione: si chiude insieme al caster
+impl Drop for TcpCaster {
+    fn drop(&mut self) {
+        self.accept_task.abort();
+    }
+}
+
+async fn accept_connections(
+    listener: TcpListener,
+    connections: TcpConnections,
+    events_tx: mpsc::UnboundedSender<TransportEvent>,
+) {
+    loop {
+        let (stream, from) = match listener.accept().await {
+            Ok(accepted) => accepted,
+            Err(e) => {
+                // Errori come il limite di descrittori sono temporanei: si riprova
+                eprintln!("Errore durante l'accettazione di una connessione: {}", e);
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                continue;
+            }
+        };
+        let from = from.to_string();
+        let _ = stream.set_nodelay(true);
+        let (mut reader, writer) = stream.into_split();
+        connections
+            .write()
+            .await
+            .insert(from.clone(), Arc::new(Mutex::new(writer)));
+
+        // Ogni connessione ha un task che inoltra i messaggi di controllo
+        let connections = connections.clone();
+        let events_tx = events_tx.clone();
+        tokio::spawn(async move {
+            loop {
+                match read_message(&mut reader).await {
+                    Ok((_, data)) => {
+                        let event = TransportEvent::Message {
+                            data,
+                            from: from.clone(),
+                        };
+                        if events_tx.send(event).is_err() {
+                            break;
+                        }
+                    }
+                    Err(_) => {
+                        connections.write().await.remove(&from);
+                        let _ = events_tx.send(TransportEvent::Closed(from));
+                        break;
+                    }
+                }
+            }
+        });
+    }
+}
+
+impl CasterTransport for TcpCaster {
+    fn packetize(&self, _frame_id: u32, data: &[u8], _redundancy: Redundancy) -> Vec<Vec<u8>> {
+        // TCP garantisce ordine, integrità e consegna: basta il prefisso con la lunghezza
+        vec![tcp_message(PayloadKind::Frame, data)]
+    }
+
+    async fn send_packets(&self, address: &str, packets: &[Vec<u8>]) -> io::Result<()> {
+        // La mappa resta bloccata solo per clonare la connessione: un receiver lento
+        // non blocca né gli altri invii né le nuove connessioni
+        let writer = self.connections.read().await.get(address).cloned();
+        let Some(writer) = writer else {
+            return Err(io::Error::new(io::ErrorKind::NotConnected, "Receiver non connesso"));
+        };
+        let mut writer = writer.lock().await;
+        let write = async {
+            for packet in packets {
+                writer.write_all(packet).await?;
+            }
+            Ok(())
+        };
+        tokio::time::timeout(TCP_WRITE_TIMEOUT, write)
+            .await
+            .unwrap_or_else(|_| Err(io::Error::new(io::ErrorKind::TimedOut, "Receiver troppo lento")))
+    }
+
+    async fn send_control(&self, address: &str, data: &[u8]) -> io::Result<()> {
+        self.send_packets(address, &[tcp_message(PayloadKind::Control, data)])
+            .await
+    }
+
+    async fn next_event(&self) -> io::Result<TransportEvent> {
+        // Sia il lock sia `recv` sono cancel-safe: nessun evento va perso se il future viene scartato
+        let mut events_rx = self.events_rx.lock().await;
+        events_rx
+            .recv()
+            .await
+            .ok_or_else(|| io::Error::from(io::ErrorKind::BrokenPipe))
+    }
+}
+
+/// Trasporto del receiver, dello stesso tipo scelto dal caster.
+#[derive(Debug)]
+pub enum ReceiverChannel {
+    Udp(UdpReceiver),
+    Tcp(TcpReceiver),
+    Multicast(MulticastReceiver),
+}
+
+impl ReceiverChannel {
+    pub async fn bind(address: &str, kind: TransportKind, stats: Arc<ReceiverStats>) -> io::Result<Self> {
+        Ok(match kind {
+            TransportKind::Udp => ReceiverChannel::Udp(UdpReceiver::bind(address, stats).await?),
+            // La connessione viene aperta alla registrazione
+            TransportKind::Tcp => ReceiverChannel::Tcp(TcpReceiver {
+                reader: Mutex::new(None),
+                writer: Mutex::new(None),
+                stats,
+            }),
+            TransportKind::Multicast => ReceiverChannel::Multicast(MulticastReceiver {
+                unicast: UdpReceiver::bind(address, stats).await?,
+                group_socket: OnceLock::new(),
+            }),
+        })
+    }
+
+    /// Si unisce al gruppo su cui il caster invia i frame; non ha effetto se non è multicast.
+    pub fn join_group(&self, group: SocketAddrV4) -> io::Result<()> {
+        match self {
+            ReceiverChannel::Multicast(multicast) => multicast.join(group),
+            ReceiverChannel::Udp(_) | ReceiverChannel::Tcp(_) => Ok(()),
+        }
+    }
+
+    /// Indirizzo locale su cui arrivano i frame; per TCP è noto solo dopo la connessione.
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        match self {
+            ReceiverChannel::Udp(udp) => udp.socket.local_addr().ok(),
+            ReceiverChannel::Tcp(_) => None,
+            ReceiverChannel::Multicast(multicast) => multicast.unicast.socket.local_addr().ok(),
+        }
+    }
+}
+
+impl ReceiverTransport for ReceiverChannel {
+    async fn connect(&self, caster: &str) -> io::Result<()> {
+        match self {
+            ReceiverChannel::Udp(udp) => udp.connect(caster).await,
+            ReceiverChannel::Tcp(tcp) => tcp.connect(caster).await,
+            ReceiverChannel::Multicast(multicast) => multicast.connect(caster).await,
+        }
+    }
+
+    async fn send_control(&self, caster: &str, data: &[u8]) -> io::Result<()> {
+        match self {
+            ReceiverChannel::Udp(udp) => udp.send_control(caster, data).await,
+            ReceiverChannel::Tcp(tcp) => tcp.send_control(caster, data).await,
+            ReceiverChannel::Multicast(multicast) => multicast.send_control(caster, data).await,
+        }
+    }
+
+    async fn recv(&self) -> Result<Incoming, Box<dyn std::error::Error + Send + Sync>> {
+        match self {
+            ReceiverChannel::Udp(udp) => udp.recv().await,
+            ReceiverChannel::Tcp(tcp) => tcp.recv().await,
+            ReceiverChannel::Multicast(multicast) => multicast.recv().await,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct UdpReceiver {
+    socket: UdpSocket,
+    reassembler: std::sync::Mutex<FrameReassembler>,
+}
+
+impl UdpReceiver {
+    async fn bind(address: &str, stats: Arc<ReceiverStats>) -> io::Result<Self> {
+        let socket = UdpSocket::bind(address).await?;
+        set_buffer_sizes(&socket);
+        Ok(UdpReceiver {
+            socket,
+            reassembler: std::sync::Mutex::new(FrameReassembler::new(
+                DEFAULT_REASSEMBLY_TIMEOUT,
+                stats,
+            )),
+        })
+    }
+
+    pub fn reassembler(&self) -> &std::sync::Mutex<FrameReassembler> {
+        &self.reassembler
+    }
+}
+
+impl ReceiverTransport for UdpReceiver {
+    async fn connect(&self, _caster: &str) -> io::Result<()> {
+        Ok(())
+    }
+
+    async fn send_control(&self, caster: &str, data: &[u8]) -> io::Result<()> {
+        self.socket.send_to(data, caster).await.map(|_| ())
+    }
+
+    async fn recv(&self) -> Result<Incoming, Box<dyn std::error::Error + Send + Sync>> {
+        recv_reassembled(&self.socket, &self.reassembler).await
+    }
+}
+
+/// Receiver multicast: si registra in unicast e riceve i frame dal gruppo.
+#[derive(Debug)]
+pub struct MulticastReceiver {
+    unicast: UdpReceiver,
+    group_socket: OnceLock<UdpSocket>, // Disponibile dopo l'accettazione del caster
+}
+
+impl MulticastReceiver {
+    pub fn reassembler(&self) -> &std::sync::Mutex<FrameReassembler> {
+        self.unicast.reassembler()
+    }
+
+    /// Si unisce al gruppo comunicato dal caster; le chiamate successive non hanno effetto.
+    pub fn join(&self, group: SocketAddrV4) -> io::Result<()> {
+        if self.group_socket.get().is_none() {
+            let _ = self.group_socket.set(self.join_group(group)?);
+        }
+        Ok(())
+    }
+
+    // Socket in ascolto sulla porta del gruppo; SO_REUSEADDR (e SO_REUSEPORT sui
+    // sistemi BSD) permette a più receiver sulla stessa macchina di unirsi allo stesso gruppo.
+    fn join_group(&self, group: SocketAddrV4) -> io::Result<UdpSocket> {
+        let interface = match self.unicast.socket.local_addr()? {
+            SocketAddr::V4(local) => *local.ip(),
+            SocketAddr::V6(_) => Ipv4Addr::UNSPECIFIED,
+        };
+        let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
+        socket.set_reuse_address(true)?;
+        #[cfg(any(
+            target_os = "macos",
+            target_os = "ios",
+            target_os = "freebsd",
+            target_os = "openbsd",
+            target_os = "netbsd",
+            target_os = "dragonfly"
+        ))]
+        socket.set_reuse_port(true)?;
+        socket.bind(&SocketAddr::from((Ipv4Addr::UNSPECIFIED, group.port())).into())?;
+        socket.join_multicast_v4(group.ip(), &interface)?;
+        socket.set_nonblocking(true)?;
+        let socket = UdpSocket::from_std(socket.into())?;
+        set_buffer_sizes(&socket);
+        Ok(socket)
+    }
+}
+
+impl ReceiverTransport for MulticastReceiver {
+    // Il gruppo è noto solo dopo l'accettazione, vedi `join`
+    async fn connect(&self, _caster: &str) -> io::Result<()> {
+        Ok(())
+    }
+
+    async fn send_control(&self, caster: &str, data: &[u8]) -> io::Result<()> {
+        self.unicast.send_control(caster, data).await
+    }
+
+    async fn recv(&self) -> Result<Incoming, Box<dyn std::error::Error + Send + Sync>> {
+        // I frame arrivano dal gruppo, i messaggi di controllo sulla socket unicast
+        match self.group_socket.get() {
+            Some(group_socket) => tokio::select! {
+                incoming = recv_reassembled(group_socket, self.unicast.reassembler()) => incoming,
+                incoming = self.unicast.recv() => incoming,
+            },
+            None => self.unicast.recv().await,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct TcpReceiver {
+    reader: Mutex<Option<OwnedReadHalf>>,
+    writer: Mutex<Option<OwnedWriteHalf>>,
+    stats: Arc<ReceiverStats>,
+}
+
+impl ReceiverTransport for TcpReceiver {
+    async fn connect(&self, caster: &str) -> io::Result<()> {
+        let stream = TcpStream::connect(caster).await?;
+        stream.set_nodelay(true)?;
+        let (reader, writer) = stream.into_split();
+        *self.reader.lock().await = Some(reader);
+        *self.writer.lock().await = Some(writer);
+        Ok(())
+    }
+
+    async fn send_control(&self, _caster: &str, data: &[u8]) -> io::Result<()> {
+        let mut writer = self.writer.lock().await;
+        let writer = writer
+            .as_mut()
+            .ok_or_else(|| io::Error::from(io::ErrorKind::NotConnected))?;
+        writer.write_all(&tcp_message(PayloadKind::Control, data)).await
+    }
+
+    async fn recv(&self) -> Result<Incoming, Box<dyn std::error::Error + Send + Sync>> {
+        let mut reader = self.reader.lock().await;
+        let reader = reader
+            .as_mut()
+            .ok_or_else(|| io::Error::from(io::ErrorKind::NotConnected))?;
+        match read_message(reader).await? {
+            (PayloadKind::Control, data) => Ok(Incoming::Control(data)),
+            (PayloadKind::Frame, data) => {
+                self.stats.frame_completed();
+                Ok(Incoming::Frame(data))
+            }
+            // Il caster non aggiunge parità su TCP
+            (PayloadKind::Parity, _) => {
+                Err(io::Error::new(io::ErrorKind::InvalidData, "Parità inattesa su TCP").into())
+            }
+        }
+    }
+}
+
+// Riceve datagrammi finché il reassembler non completa un frame o arriva un messaggio di controllo
+async fn recv_reassembled(
+    socket: &UdpSocket,
+    reassembler: &std::sync::Mutex<FrameReassembler>,
+) -> Result<Incoming, Box<dyn std::error::Error + Send + Sync>> {
+    let mut buf = vec![0u8; MAX_PAYLOAD];
+    loop {
+        let received_bytes = socket.recv(&mut buf).await?;
+        // Un datagramma estraneo o corrotto non interrompe la ricezione: viene solo contato
+        let Ok((header, payload)) = PacketHeader::decode(&buf[..received_bytes]) else {
+            reassembler.lock().unwrap().stats().packet_rejected();
+            continue;
+        };
+
+        if header.kind == PayloadKind::Control {
+            return Ok(Incoming::Control(payload.to_vec()));
+        }
+        if let Some(data) = reassembler.lock().unwrap().push(&header, payload) {
+            return Ok(Incoming::Frame(data));
+        }
+    }
+}
+
+// Il sistema può ridurre le dimensioni ai suoi limiti: un errore non impedisce lo streaming
+fn set_buffer_sizes(socket: &UdpSocket) {
+    let socket = SockRef::from(socket);
+    if let Err(e) = socket
+        .set_send_buffer_size(SOCKET_BUFFER_SIZE)
+        .and_then(|_| socket.set_recv_buffer_size(SOCKET_BUFFER_SIZE))
+    {
+        eprintln!("Impossibile impostare i buffer della socket: {}", e);
+    }
+}
+
+// Messaggio TCP: lunghezza (4, big-endian, tipo incluso) | tipo (1) | dati
+fn tcp_message(kind: PayloadKind, data: &[u8]) -> Vec<u8> {
+    let mut message = Vec::with_capacity(5 + data.len());
+    message.extend(&(data.len() as u32 + 1).to_be_bytes());
+    message.push(kind as u8);
+    message.extend(data);
+    message
+}
+
+async fn read_message<R: AsyncRead + Unpin>(reader: &mut R) -> io::Result<(PayloadKind, Vec<u8>)> {
+    let len = reader.read_u32().await?;
+    if len == 0 || len > MAX_TCP_MESSAGE {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Lunghezza del messaggio non valida ({} byte)", len),
+        ));
+    }
+    let kind = PayloadKind::try_from(reader.read_u8().await?)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    let mut data = vec![0u8; len as usize - 1];
+    reader.read_exact(&mut data).await?;
+    Ok((kind, data))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::socket::test_util::{connect_receiver, loopback, pattern, recv_frame};
+
+    #[tokio::test]
+    async fn unicast_transports_roundtrip() {
+        for kind in [TransportKind::Udp, TransportKind::Tcp] {
+            let (caster, receiver, address, stats) = loopback(kind).await;
+            let payload = pattern(10_000);
+            caster.send_packets(&address, &caster.packetize(7, &payload, Redundancy::Off)).await.unwrap();
+            assert_eq!(recv_frame(&receiver).await, payload, "{}", kind);
+            assert_eq!(stats.frames_received(), 1, "{}", kind);
+        }
+    }
+
+    #[tokio::test]
+    async fn parity_recovers_a_lost_chunk() {
+        let (caster, receiver, address, stats) = loopback(TransportKind::Udp).await;
+        let payload = pattern(10_000);
+        let mut packets = caster.packetize(3, &payload, Redundancy::Percent(20));
+        // Il secondo chunk di dati va perso: la parità lo ricostruisce
+        packets.remove(1);
+        caster.send_packets(&address, &packets).await.unwrap();
+        assert_eq!(recv_frame(&receiver).await, payload);
+        assert_eq!(stats.chunks_recovered(), 1);
+    }
+
+    #[tokio::test]
+    async fn stray_datagrams_are_counted_and_skipped() {
+        let (caster, receiver, address, stats) = loopback(TransportKind::Udp).await;
+
+        let stray = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        stray.send_to(b"not a screencast packet", &address).await.unwrap();
+        let payload = pattern(3000);
+        caster.send_packets(&address, &caster.packetize(1, &payload, Redundancy::Off)).await.unwrap();
+        assert_eq!(recv_frame(&receiver).await, payload);
+        assert_eq!(stats.packets_rejected(), 1);
+    }
+
+    #[tokio::test]
+    async fn tcp_events_survive_a_cancelled_next_event() {
+        let caster = CasterChannel::bind("127.0.0.1:0", TransportKind::Tcp).await.unwrap();
+        let caster_addr = caster.local_addr().unwrap().to_string();
+
+        // Un'attesa interrotta prima che arrivi qualcosa non deve perdere la connessione
+        let _ = tokio::time::timeout(Duration::from_millis(20), caster.next_event()).await;
+        let receiver = ReceiverChannel::bind("127.0.0.1:0", TransportKind::Tcp, Arc::default()).await.unwrap();
+        receiver.connect(&caster_addr).await.unwrap();
+        receiver.send_control(&caster_addr, b"hello").await.unwrap();
+        match caster.next_event().await.unwrap() {
+            TransportEvent::Message { data, .. } => assert_eq!(data, b"hello"),
+            other => panic!("atteso un messaggio, ricevuto {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn tcp_closed_connections_are_reported() {
+        let (caster, receiver, address, _) = loopback(TransportKind::Tcp).await;
+        drop(receiver);
+        match caster.next_event().await.unwrap() {
+            TransportEvent::Closed(from) => assert_eq!(from, address),
+            other => panic!("attesa la chiusura, ricevuto {:?}", other),
+        }
+        let error = caster.send_packets(&address, &caster.packetize(0, b"frame", Redundancy::Off)).await.unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::NotConnected);
+    }
+
+    #[test]
+    fn multicast_group_depends_on_caster_address() {
+        let first = multicast_group("192.168.1.10:7878".parse().unwrap());
+        let second = multicast_group("192.168.1.11:7878".parse().unwrap());
+        let other_port = multicast_group("192.168.1.10:7880".parse().unwrap());
+        assert!(first.ip().is_multicast());
+        assert_eq!(first.port(), 7879);
+        assert_ne!(first, second);
+        assert_ne!(first, other_port);
+        assert_eq!(multicast_group("10.0.0.1:65535".parse().unwrap()).port(), 65534);
+    }
+
+    #[test]
+    fn multicast_group_never_collides_with_discovery() {
+        // Un caster sulla porta precedente a quella della discovery la salta
+        let before_discovery = SocketAddr::from((Ipv4Addr::LOCALHOST, DISCOVERY_GROUP.port() - 1));
+        assert_eq!(multicast_group(before_discovery).port(), DISCOVERY_GROUP.port() + 1);
+        for port in 7000..9000 {
+            let group = multicast_group(SocketAddr::from((Ipv4Addr::new(192, 168, 1, 10), port)));
+            assert_ne!(group.ip(), DISCOVERY_GROUP.ip());
+            assert_ne!(group.port(), DISCOVERY_GROUP.port());
+        }
+    }
+
+    #[tokio::test]
+    async fn multicast_frames_reach_every_receiver() {
+        let caster = CasterChannel::bind("127.0.0.1:0", TransportKind::Multicast).await.unwrap();
+        let group = caster.multicast_group().unwrap();
+        assert_eq!(group, multicast_group(caster.local_addr().unwrap()));
+        let mut receivers = vec![];
+        for _ in 0..2 {
+            receivers.push(connect_receiver(&caster, TransportKind::Multicast).await);
+        }
+
+        let payload = pattern(5000);
+        let packets = caster.packetize(1, &payload, Redundancy::Off);
+        // L'indirizzo viene ignorato: i frame vanno al gruppo
+        caster.send_packets(&receivers[0].1, &packets).await.unwrap();
+        for (receiver, _, _) in &receivers {
+            assert_eq!(recv_frame(receiver).await, payload);
+        }
+    }
+}